@@ -0,0 +1,266 @@
+//! Render spectra to PNG or SVG files without opening a window, e.g., in CI or on a server.
+//!
+//! Run `cargo run --bin render_cli --features render_cli -- --help` for the usage.
+
+use std::{error::Error, fs, path::Path};
+
+use slint_futuresdr_demo::{
+    rendering::{
+        auto_range::AutoRange,
+        config::{PlotConfig, YRange},
+        render,
+        snapshot::SnapshotFormat,
+        theme::PlotTheme,
+    },
+    vector, FFT_SIZE,
+};
+
+const USAGE: &str = "\
+Usage: render_cli [OPTIONS] <INPUT> <OUTPUT>
+
+INPUT is one of
+  test-data           the recording compiled into the binary
+  <FILE>.rs           a recording written by the `record_to_file` binary
+  <FILE>              raw little-endian f32 magnitudes
+  ws://<HOST>:<PORT>  a websocket stream, e.g., from the `websocket_tx` binary
+
+OUTPUT is a .png or .svg file. If more than one frame is rendered, the frame number is appended to
+the file name, e.g., `spectrum_00001.png`.
+
+Options:
+  --frames <N>       number of frames to render, 0 for all [default: 1]
+  --skip <N>         number of frames to skip first [default: 0]
+  --fft-size <N>     bins per frame of raw files [default: 512]
+  --width <N>        image width in pixels [default: 1280]
+  --height <N>       image height in pixels [default: 720]
+  --y-min <LEVEL>    lower y-axis limit, auto-ranged if not given together with --y-max
+  --y-max <LEVEL>    upper y-axis limit
+  --fps <RATE>       frame rate assumed for smoothing the auto-range of sequences [default: 10]
+  --theme <NAME>     light, dark or high-contrast [default: light]
+  -h, --help         print this help";
+
+struct Options {
+    input: String,
+    output: String,
+    frames: usize,
+    skip: usize,
+    fft_size: usize,
+    fps: f64,
+    config: PlotConfig,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut positional = Vec::new();
+        let (mut frames, mut skip, mut fft_size) = (1, 0, FFT_SIZE);
+        let (mut width, mut height) = (1280, 720);
+        let (mut y_min, mut y_max) = (None, None);
+        let mut fps = 10.0;
+        let mut theme = PlotTheme::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "--frames" => frames = value()?.parse()?,
+                "--skip" => skip = value()?.parse()?,
+                "--fft-size" => fft_size = value()?.parse()?,
+                "--width" => width = value()?.parse()?,
+                "--height" => height = value()?.parse()?,
+                "--y-min" => y_min = Some(value()?.parse()?),
+                "--y-max" => y_max = Some(value()?.parse()?),
+                "--fps" => fps = value()?.parse()?,
+                "--theme" => {
+                    theme = match value()?.as_str() {
+                        "light" => PlotTheme::light(),
+                        "dark" => PlotTheme::dark(),
+                        "high-contrast" => PlotTheme::high_contrast(),
+                        other => return Err(format!("unknown theme {other}").into()),
+                    }
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}").into()),
+                _ => positional.push(arg),
+            }
+        }
+
+        let [input, output] = <[String; 2]>::try_from(positional)
+            .map_err(|_| "expected exactly one INPUT and one OUTPUT")?;
+        if width == 0 || height == 0 || fft_size == 0 {
+            return Err("width, height and FFT size must be greater than 0".into());
+        }
+        Ok(Self {
+            input,
+            output,
+            frames,
+            skip,
+            fft_size,
+            fps,
+            config: PlotConfig {
+                width,
+                height,
+                y_range: match y_min.zip(y_max) {
+                    Some((y_min, y_max)) => YRange::Fixed(y_min, y_max),
+                    None => YRange::Fit,
+                },
+                theme,
+                ..PlotConfig::default()
+            },
+        })
+    }
+}
+
+/// Renders frames to files one by one
+struct Renderer {
+    options: Options,
+    format: SnapshotFormat,
+    auto_range: AutoRange,
+    rendered: usize,
+}
+
+impl Renderer {
+    /// Render `frame` unless it is skipped. Returns whether more frames are wanted.
+    fn render(&mut self, index: usize, frame: &[f32]) -> Result<bool, Box<dyn Error>> {
+        let options = &self.options;
+        if index < options.skip {
+            return Ok(true);
+        }
+
+        // Sequences are smoothed like in the GUI, single frames are fitted exactly
+        let mut config = options.config.clone();
+        if config.y_range == YRange::Fit && options.frames != 1 {
+            let now = self.rendered as f64 / options.fps;
+            if let Some((y_min, y_max)) = self.auto_range.update(frame, &config.auto_range, now) {
+                config.y_range = YRange::Fixed(y_min, y_max);
+            }
+        }
+
+        let bytes = render(frame, &config, self.format)?.image;
+        let file_name = if options.frames == 1 {
+            options.output.clone()
+        } else {
+            numbered(&options.output, index)
+        };
+        fs::write(&file_name, bytes)?;
+        eprintln!("Rendered frame {index} to {file_name}");
+
+        self.rendered += 1;
+        Ok(options.frames == 0 || self.rendered < options.frames)
+    }
+}
+
+/// `spectrum.png` becomes `spectrum_00042.png` for `index` 42
+fn numbered(file_name: &str, index: usize) -> String {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    path.with_file_name(format!("{stem}_{index:05}.{extension}"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Read a recording written by `record_to_file`, which declares `TEST_DATA` and `FFT_SIZE`
+/// constants. Returns the values and the FFT size.
+fn parse_recording(source: &str) -> Result<(Vec<f32>, Option<usize>), Box<dyn Error>> {
+    let data_start = source
+        .find("TEST_DATA")
+        .and_then(|i| source[i..].find("&[").map(|j| i + j + 2))
+        .ok_or("no TEST_DATA in recording")?;
+    let data_end = data_start
+        + source[data_start..]
+            .find(']')
+            .ok_or("unterminated TEST_DATA")?;
+    let data = source[data_start..data_end]
+        .split(',')
+        .map(|value| value.trim().trim_end_matches("f32"))
+        .filter(|value| !value.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+
+    let fft_size = source.find("FFT_SIZE").and_then(|i| {
+        let declaration = &source[i..];
+        let value = &declaration[declaration.find('=')? + 1..declaration.find(';')?];
+        value.trim().trim_end_matches("usize").parse().ok()
+    });
+    Ok((data, fft_size))
+}
+
+/// Render frames from a file or the compiled-in recording
+fn render_recording(renderer: &mut Renderer) -> Result<(), Box<dyn Error>> {
+    let input = &renderer.options.input;
+    let (data, fft_size) = if input == "test-data" {
+        (vector::TEST_DATA.to_vec(), vector::FFT_SIZE)
+    } else if input.ends_with(".rs") {
+        let (data, fft_size) = parse_recording(&fs::read_to_string(input)?)?;
+        (data, fft_size.unwrap_or(renderer.options.fft_size))
+    } else {
+        let data = fs::read(input)?
+            .chunks_exact(4)
+            .map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]]))
+            .collect();
+        (data, renderer.options.fft_size)
+    };
+
+    for (index, frame) in data.chunks_exact(fft_size).enumerate() {
+        if !renderer.render(index, frame)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Render frames as they arrive from a websocket until enough were rendered or it is closed
+fn render_websocket(renderer: &mut Renderer) -> Result<(), Box<dyn Error>> {
+    use futures_util::StreamExt;
+    use tokio_tungstenite_wasm::Message;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let socket = tokio_tungstenite_wasm::connect(renderer.options.input.as_str()).await?;
+        let (_, mut read) = socket.split();
+
+        let mut index = 0;
+        while let Some(message) = read.next().await {
+            match message? {
+                Message::Binary(data) => {
+                    let frame: Vec<f32> = data
+                        .chunks_exact(4)
+                        .map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]]))
+                        .collect();
+                    if frame.is_empty() {
+                        continue;
+                    }
+                    if !renderer.render(index, &frame)? {
+                        break;
+                    }
+                    index += 1;
+                }
+                Message::Close(_) => break,
+                Message::Text(_) => eprintln!("Ignoring text message"),
+            }
+        }
+        Ok::<_, Box<dyn Error>>(())
+    })
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options =
+        Options::parse(std::env::args().skip(1)).inspect_err(|_| eprintln!("{USAGE}\n"))?;
+    let format = SnapshotFormat::from_file_name(&options.output)
+        .ok_or("OUTPUT must end with .png or .svg")?;
+
+    let websocket = options.input.starts_with("ws://") || options.input.starts_with("wss://");
+    let mut renderer = Renderer {
+        options,
+        format,
+        auto_range: AutoRange::default(),
+        rendered: 0,
+    };
+    if websocket {
+        render_websocket(&mut renderer)
+    } else {
+        render_recording(&mut renderer)
+    }
+}
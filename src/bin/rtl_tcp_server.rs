@@ -0,0 +1,153 @@
+//! Stand-in for `rtl_tcp` that replays a `cu8` capture, e.g., recorded with `rtl_sdr`, instead of
+//! streaming from a dongle. Useful to try the rtl_tcp source of the plotter without hardware.
+//!
+//! Run `cargo run --bin rtl_tcp_server -- --help` for the usage.
+
+use std::{
+    error::Error,
+    fs,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "\
+Usage: rtl_tcp_server [OPTIONS] <FILE>
+
+FILE contains interleaved 8 bit unsigned I and Q samples, as written by `rtl_sdr`. It is streamed
+in a loop to one client at a time, at the sample rate requested by the client. Commands received
+from the client are printed, but apart from the sample rate they have no effect.
+
+Options:
+  --port <PORT>      TCP port to listen on [default: 1234]
+  --rate <RATE>      samples per second until the client sets one [default: 2048000]
+  --tuner <TYPE>     tuner type sent in the header, 5 is an R820T [default: 5]
+  -h, --help         print this help";
+
+/// Number of gain steps of an R820T, the gains themselves are not part of the protocol
+const GAIN_COUNT: u32 = 29;
+/// Interval in which the samples are sent
+const INTERVAL: Duration = Duration::from_millis(10);
+
+struct Options {
+    file: String,
+    port: u16,
+    rate: u32,
+    tuner: u32,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Box<dyn Error>> {
+        let mut file = None;
+        let (mut port, mut rate, mut tuner) = (1234, 2_048_000, 5);
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {arg}"));
+            match arg.as_str() {
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                "--port" => port = value()?.parse()?,
+                "--rate" => rate = value()?.parse()?,
+                "--tuner" => tuner = value()?.parse()?,
+                _ if arg.starts_with("--") => return Err(format!("unknown option {arg}").into()),
+                _ if file.is_none() => file = Some(arg),
+                _ => return Err("expected exactly one FILE".into()),
+            }
+        }
+
+        Ok(Self {
+            file: file.ok_or("expected exactly one FILE")?,
+            port,
+            rate: rate.max(1),
+            tuner,
+        })
+    }
+}
+
+/// Print the commands of the client and apply sample rate changes to `rate`
+fn handle_commands(mut stream: TcpStream, rate: Arc<AtomicU32>) {
+    let mut command = [0; 5];
+    while stream.read_exact(&mut command).is_ok() {
+        let param = u32::from_be_bytes([command[1], command[2], command[3], command[4]]);
+        match command[0] {
+            0x01 => println!("Set frequency to {param} Hz"),
+            0x02 => {
+                println!("Set sample rate to {param} S/s");
+                rate.store(param.max(1), Ordering::Relaxed);
+            }
+            0x03 => println!(
+                "Set gain mode to {}",
+                ["auto", "manual"][(param != 0) as usize]
+            ),
+            0x04 => println!("Set gain to {:.1} dB", param as f32 / 10.0),
+            other => println!("Ignoring command {other:#04x} with {param}"),
+        }
+    }
+}
+
+/// Send the header and then `samples` in a loop until the client disconnects
+fn serve(mut stream: TcpStream, samples: &[u8], options: &Options) -> std::io::Result<()> {
+    let rate = Arc::new(AtomicU32::new(options.rate));
+    let commands = stream.try_clone()?;
+    let command_rate = rate.clone();
+    thread::spawn(move || handle_commands(commands, command_rate));
+
+    let mut header = *b"RTL0\0\0\0\0\0\0\0\0";
+    header[4..8].copy_from_slice(&options.tuner.to_be_bytes());
+    header[8..].copy_from_slice(&GAIN_COUNT.to_be_bytes());
+    stream.write_all(&header)?;
+
+    // Keep track of the samples that are due, so the rate doesn't drift with the sleep accuracy
+    let mut position = 0;
+    let mut due = 0.0;
+    let mut last = Instant::now();
+    loop {
+        thread::sleep(INTERVAL);
+        let now = Instant::now();
+        due += now.duration_since(last).as_secs_f64() * rate.load(Ordering::Relaxed) as f64;
+        last = now;
+
+        let mut bytes = 2 * due as usize;
+        due = due.fract();
+        while bytes > 0 {
+            let n = bytes.min(samples.len() - position);
+            stream.write_all(&samples[position..position + n])?;
+            position = (position + n) % samples.len();
+            bytes -= n;
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let options =
+        Options::parse(std::env::args().skip(1)).inspect_err(|_| eprintln!("{USAGE}\n"))?;
+    let mut samples = fs::read(&options.file)?;
+    // Only complete samples are replayed, so I and Q stay aligned when looping
+    samples.truncate(samples.len() / 2 * 2);
+    if samples.is_empty() {
+        return Err(format!("{} contains no samples", options.file).into());
+    }
+
+    let listener = TcpListener::bind(("0.0.0.0", options.port))?;
+    println!("Replaying {} on port {}", options.file, options.port);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        println!("Client {peer} connected");
+        match serve(stream, &samples, &options) {
+            Err(e) if matches!(e.kind(), ErrorKind::BrokenPipe | ErrorKind::ConnectionReset) => {
+                println!("Client {peer} disconnected");
+            }
+            Err(e) => eprintln!("Client {peer} failed: {e}"),
+            Ok(()) => {}
+        }
+    }
+    Ok(())
+}
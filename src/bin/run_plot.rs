@@ -1,13 +1,27 @@
-use log::info;
-use slint::ComponentHandle;
-use slint_futuresdr_demo::{wait_for_samples, MainWindow};
+use log::{info, warn};
+use slint::{ComponentHandle, Model};
+use slint_futuresdr_demo::{
+    error::{self, Error, Result},
+    export::{self, DataFormat},
+    history,
+    rendering::{channels, snapshot::save_snapshot, theme::PlotTheme, view},
+    settings::Settings,
+    supervisor::Supervisor,
+    wait_for_samples, MainWindow, PlotMeta,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    slint::{ModelRc, VecModel},
+    slint_futuresdr_demo::{reference, remote},
+};
 
 #[cfg(feature = "record_to_file")]
 compile_error!("Must run `record_to_file` bin when spcifying `record_to_file` feature");
 
 // Call the lib function from the bin for wasm compatibility
 //https://internals.rust-lang.org/t/pre-issue-feature-request-give-me-the-option-to-not-build-a-target/18852/12
-pub fn main() {
+pub fn main() -> Result<()> {
     // This provides better error messages in debug mode.
     // It's disabled in release mode so it doesn't bloat up the file size.
     #[cfg(all(debug_assertions, target_arch = "wasm32"))]
@@ -22,22 +36,193 @@ pub fn main() {
     }
     info!("starting");
 
-    let app = MainWindow::new().unwrap();
+    let app = MainWindow::new()?;
+
+    // Run the sample generation while the plot is enabled
+    let supervisor = Supervisor::new(app.as_weak(), wait_for_samples);
+    let window_weak = app.as_weak();
+    let toggled_supervisor = supervisor.clone();
+    app.on_plot_enable_toggled(move || {
+        toggled_supervisor.set_enabled(window_weak.unwrap().get_plot_enable())
+    });
+    let changed_supervisor = supervisor.clone();
+    app.on_source_changed(move || changed_supervisor.restart());
+    // The banner is already cleared, restart the source in case it failed
+    app.on_retry(move || supervisor.restart());
+
+    #[cfg(feature = "native_plot")]
+    app.set_native_plot(true);
+
+    // The browser page has a dark background, so start with a matching theme
+    #[cfg(target_arch = "wasm32")]
+    app.global::<slint_futuresdr_demo::Theme>().set_preset(1);
+
+    let window_weak = app.as_weak();
+    app.on_theme_changed(move || {
+        let app = window_weak.unwrap();
+        PlotTheme::from_app(&app).apply_to_ui(&app);
+    });
+
+    let window_weak = app.as_weak();
+    app.on_channel_visible_changed(move |index, visible| {
+        channels::set_visible(&window_weak.unwrap(), index as usize, visible)
+    });
+    let window_weak = app.as_weak();
+    app.on_channel_offset_changed(move |index, offset| {
+        channels::set_offset(&window_weak.unwrap(), index as usize, offset)
+    });
+
+    // Everything set up to here are the defaults, restore the settings of the last session on top
+    let default_settings = Settings::defaults(&app);
+    let settings = Settings::load(&default_settings);
+    apply_settings(&app, &settings);
+
+    let window_weak = app.as_weak();
+    app.on_reset_settings(move || {
+        apply_settings(&window_weak.unwrap(), &default_settings);
+    });
+
+    // Save whenever something changed, as the event loop may never return, e.g., on WASM
+    let window_weak = app.as_weak();
+    let mut saved_settings = settings;
+    let settings_timer = slint::Timer::default();
+    settings_timer.start(
+        slint::TimerMode::Repeated,
+        std::time::Duration::from_secs(1),
+        move || {
+            let settings = Settings::from_app(&window_weak.unwrap());
+            if settings != saved_settings {
+                // Only reported once per change, the banner would reappear every second otherwise
+                if let Err(e) = settings.save() {
+                    error::report(&window_weak.unwrap(), &e);
+                }
+                saved_settings = settings;
+            }
+        },
+    );
+
+    let window_weak = app.as_weak();
+    app.on_zoom_box(move |fx0, fy0, fx1, fy1| {
+        view::zoom_to_box(&window_weak.unwrap(), (fx0, fy0), (fx1, fy1))
+    });
+    let window_weak = app.as_weak();
+    app.on_zoom_wheel(move |fx, fy, steps| {
+        view::zoom_around(&window_weak.unwrap(), (fx, fy), steps)
+    });
+    let window_weak = app.as_weak();
+    app.on_pan(move |dfx, dfy| view::pan(&window_weak.unwrap(), (dfx, dfy)));
+    let window_weak = app.as_weak();
+    app.on_reset_zoom(move || view::reset_zoom(&window_weak.unwrap()));
+    let window_weak = app.as_weak();
+    app.on_hover_info(move |fx, fy, _chart, _frame| {
+        view::hover_info(&window_weak.unwrap(), (fx, fy))
+    });
+
+    let window_weak = app.as_weak();
+    app.on_save_snapshot(move |file_name, width, height| {
+        let app = window_weak.unwrap();
+        match save_snapshot(&app, &file_name, width as u32, height as u32) {
+            Ok(true) => info!("Saved snapshot to {file_name}"),
+            Ok(false) => warn!("Nothing has been plotted yet"),
+            Err(e) => error::report(&app, &e),
+        }
+    });
 
     let window_weak = app.as_weak();
+    app.on_export_data(move |file_name, selection, frames| {
+        let app = window_weak.unwrap();
+        match export_data(&app, &file_name, selection, frames) {
+            Ok(count) => info!("Exported {count} trace(s) to {file_name}"),
+            Err(e) => error::report(&app, &e),
+        }
+    });
+
+    // Reference traces are stored on the file system, which is not available in the browser
+    #[cfg(target_arch = "wasm32")]
+    app.set_file_access(false);
+
+    // The Source tab sets up the websockets instead of the integrated flowgraph
+    #[cfg(feature = "websocket_rx")]
+    app.set_websocket_source(true);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let window_weak = app.as_weak();
+        app.on_save_reference(move |path| {
+            let app = window_weak.unwrap();
+            let trace: Vec<f32> = app.global::<PlotMeta>().get_reference().iter().collect();
+            match reference::save(path.as_str(), &trace) {
+                Ok(()) => info!("Saved reference trace to {path}"),
+                Err(source) => error::report(
+                    &app,
+                    &Error::File {
+                        path: path.to_string(),
+                        source,
+                    },
+                ),
+            }
+        });
 
-    window_weak
-        .clone()
-        .unwrap()
-        .on_plot_enable_toggled(move || {
-            // Start the sample generation when plot is enabled.
-            // The FG loop will check if the plot is still enabled and terminate the FG otherwise
-            if window_weak.unwrap().get_plot_enable() {
-                let window_weak = window_weak.clone();
-                slint::spawn_local(async move { wait_for_samples(window_weak).await }).unwrap();
+        let window_weak = app.as_weak();
+        app.on_load_reference(move |path| {
+            let app = window_weak.unwrap();
+            match reference::load(path.as_str()) {
+                Ok(trace) => {
+                    info!("Loaded reference trace from {path}");
+                    app.global::<PlotMeta>()
+                        .set_reference(ModelRc::new(VecModel::from(trace)));
+                }
+                Err(source) => error::report(
+                    &app,
+                    &Error::File {
+                        path: path.to_string(),
+                        source,
+                    },
+                ),
             }
-            ()
         });
 
-    app.run().unwrap();
+        // Scripts can control the app like the user, see `remote` for the endpoints
+        remote::start(app.as_weak());
+    }
+
+    app.run()?;
+
+    // The window is gone, so the error can only be logged
+    if let Err(e) = Settings::from_app(&app).save() {
+        warn!("{e}");
+    }
+    Ok(())
+}
+
+/// Export the traces picked by `selection` to `file_name`, returning how many were exported
+fn export_data(app: &MainWindow, file_name: &str, selection: i32, frames: i32) -> Result<usize> {
+    let format = DataFormat::from_file_name(file_name).ok_or_else(|| {
+        Error::Export(format!("file name {file_name} must end with .csv or .npy"))
+    })?;
+    let meta = app.global::<PlotMeta>();
+    // Same order as the selection in the GUI
+    let traces = match selection {
+        0 => vec![meta.get_last_frame().iter().collect()],
+        1 => vec![meta.get_reference().iter().collect()],
+        _ => history::latest(frames.max(1) as usize),
+    };
+    if traces.iter().all(Vec::is_empty) {
+        return Err(Error::Export("no data available yet".into()));
+    }
+
+    let bytes = format.encode(&traces, meta.get_sample_rate())?;
+    export::save_file(file_name, &bytes, format.mime_type()).map_err(|source| Error::File {
+        path: file_name.to_string(),
+        source,
+    })?;
+    Ok(traces.len())
+}
+
+/// Apply `settings` to the UI and react to them like to user input
+fn apply_settings(app: &MainWindow, settings: &Settings) {
+    settings.apply(app);
+    PlotTheme::from_app(app).apply_to_ui(app);
+    // Start or stop the sample generation
+    app.invoke_plot_enable_toggled();
 }
@@ -0,0 +1,105 @@
+//! Errors that can occur at runtime.
+//!
+//! Instead of panicking, failures are passed up to where they can be shown in the status banner of
+//! the GUI with [`report`].
+
+use std::{fmt, io};
+
+use log::warn;
+use plotters::drawing::DrawingAreaErrorKind;
+
+use crate::MainWindow;
+
+/// Result with the crate [`Error`] as default error type
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Plots cannot be rendered without any pixels, e.g., while the window is minimized
+    InvalidSize {
+        width: u32,
+        height: u32,
+    },
+    /// Plotters failed to draw the plot
+    Drawing(String),
+    /// A rendered image could not be encoded or decoded
+    Image(String),
+    /// The sample source could not be set up or failed while running
+    Source(String),
+    /// The selected data cannot be exported
+    Export(String),
+    /// A file chosen in the GUI could not be read or written
+    File {
+        path: String,
+        source: io::Error,
+    },
+    /// The settings could not be stored for the next session
+    Settings(io::Error),
+    /// The event loop is not running (anymore)
+    EventLoop(slint::EventLoopError),
+    /// The GUI could not be created or run
+    Platform(slint::PlatformError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSize { width, height } => {
+                write!(f, "cannot render a plot of {width}x{height} pixels")
+            }
+            Self::Drawing(e) => write!(f, "failed to draw plot: {e}"),
+            Self::Image(e) => write!(f, "invalid image: {e}"),
+            Self::Source(e) => write!(f, "sample source failed: {e}"),
+            Self::Export(e) => write!(f, "cannot export data: {e}"),
+            Self::File { path, source } => write!(f, "{path}: {source}"),
+            Self::Settings(e) => write!(f, "could not save settings: {e}"),
+            Self::EventLoop(e) => write!(f, "event loop unavailable: {e}"),
+            Self::Platform(e) => write!(f, "GUI platform error: {e}"),
+            Self::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::EventLoop(e) => Some(e),
+            Self::Platform(e) => Some(e),
+            Self::File { source, .. } => Some(source),
+            Self::Settings(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<slint::EventLoopError> for Error {
+    fn from(e: slint::EventLoopError) -> Self {
+        Self::EventLoop(e)
+    }
+}
+
+impl From<slint::PlatformError> for Error {
+    fn from(e: slint::PlatformError) -> Self {
+        Self::Platform(e)
+    }
+}
+
+impl<E: std::error::Error + Send + Sync> From<DrawingAreaErrorKind<E>> for Error {
+    fn from(e: DrawingAreaErrorKind<E>) -> Self {
+        Self::Drawing(e.to_string())
+    }
+}
+
+/// Log `error` and show it in the status banner of the GUI until it is dismissed or retried
+pub fn report(app: &MainWindow, error: &Error) {
+    warn!("{error}");
+    app.set_error_message(error.to_string().into());
+}
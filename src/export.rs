@@ -0,0 +1,198 @@
+//! Export spectrum data and hand exported files to the user: written to the file system on
+//! desktop and offered as a browser download on WASM.
+
+use std::{io, path::Path};
+
+use crate::error::{Error, Result};
+
+/// File formats spectrum data can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataFormat {
+    Csv,
+    /// NumPy array, load with `numpy.load`
+    Npy,
+}
+
+impl DataFormat {
+    /// Derive the format from the extension of `file_name`, e.g., `spectrum.csv`
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = Path::new(file_name).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "npy" => Some(Self::Npy),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Npy => "application/octet-stream",
+        }
+    }
+
+    /// Encode `traces`, which must all have the same number of bins, in this format
+    pub fn encode(self, traces: &[Vec<f32>], sample_rate: f32) -> Result<Vec<u8>> {
+        match self {
+            Self::Csv => encode_csv(traces, sample_rate).map(String::into_bytes),
+            Self::Npy => encode_npy(traces),
+        }
+    }
+}
+
+/// Encode `traces` as CSV with the frequency of each bin in the first column followed by one
+/// level column per trace.
+///
+/// If the `sample_rate` is unknown (i.e., not positive), the bin index is used instead of the
+/// frequency. Fails if the traces have different numbers of bins.
+pub fn encode_csv(traces: &[Vec<f32>], sample_rate: f32) -> Result<String> {
+    let bins = bins(traces)?;
+
+    let mut csv = String::from(if sample_rate > 0.0 {
+        "frequency"
+    } else {
+        "bin"
+    });
+    match traces.len() {
+        1 => csv.push_str(",level"),
+        _ => (0..traces.len()).for_each(|i| csv.push_str(&format!(",level_{i}"))),
+    }
+    csv.push('\n');
+
+    for bin in 0..bins {
+        if sample_rate > 0.0 {
+            csv.push_str(&format!("{}", bin as f32 * sample_rate / bins as f32));
+        } else {
+            csv.push_str(&format!("{bin}"));
+        }
+        for trace in traces {
+            csv.push_str(&format!(",{}", trace[bin]));
+        }
+        csv.push('\n');
+    }
+    Ok(csv)
+}
+
+/// Encode `traces` as NumPy `.npy` (version 1.0) array of little-endian `f32`.
+///
+/// The shape is `(bins,)` for a single trace and `(traces, bins)` otherwise. Fails if the traces
+/// have different numbers of bins.
+pub fn encode_npy(traces: &[Vec<f32>]) -> Result<Vec<u8>> {
+    let bins = bins(traces)?;
+    let shape = match traces.len() {
+        1 => format!("({bins},)"),
+        n => format!("({n}, {bins})"),
+    };
+
+    // The header is padded with spaces and terminated by a newline, so that the data starts at a
+    // multiple of 64 bytes
+    const PREAMBLE_LENGTH: usize = 10;
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {shape}, }}");
+    let padding = 63 - (PREAMBLE_LENGTH + header.len()) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut npy = Vec::with_capacity(PREAMBLE_LENGTH + header.len() + traces.len() * bins * 4);
+    npy.extend_from_slice(b"\x93NUMPY\x01\x00");
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+    for value in traces.iter().flatten() {
+        npy.extend_from_slice(&value.to_le_bytes());
+    }
+    Ok(npy)
+}
+
+/// The number of bins shared by all `traces`
+fn bins(traces: &[Vec<f32>]) -> Result<usize> {
+    let bins = traces.first().map_or(0, Vec::len);
+    if traces.iter().any(|trace| trace.len() != bins) {
+        return Err(Error::Export(
+            "frames have different numbers of bins".into(),
+        ));
+    }
+    Ok(bins)
+}
+
+/// Write `bytes` to the file `file_name`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_file(file_name: &str, bytes: &[u8], _mime_type: &str) -> io::Result<()> {
+    std::fs::write(file_name, bytes)
+}
+
+/// Time after which the object URL of a download is revoked to free the exported data
+#[cfg(target_arch = "wasm32")]
+const DOWNLOAD_URL_LIFETIME_MS: u32 = 60_000;
+
+/// Offer `bytes` as browser download named `file_name`
+#[cfg(target_arch = "wasm32")]
+pub fn save_file(file_name: &str, bytes: &[u8], mime_type: &str) -> io::Result<()> {
+    use wasm_bindgen::JsCast;
+
+    let js_error =
+        |e: wasm_bindgen::JsValue| io::Error::new(io::ErrorKind::Other, format!("{e:?}"));
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_(mime_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no document available"))?;
+
+    // Clicking a temporary link with the `download` attribute triggers the download
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "could not create link"))?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    // Browsers may start the download only after the click has been handled, so the object URL
+    // must stay valid for a while
+    gloo_timers::callback::Timeout::new(DOWNLOAD_URL_LIFETIME_MS, move || {
+        let _ = web_sys::Url::revoke_object_url(&url);
+    })
+    .forget();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_csv_columns() {
+        let traces = [vec![1.0, 2.0], vec![3.0, 4.0]];
+        assert_eq!(
+            encode_csv(&traces, 1000.0).unwrap(),
+            "frequency,level_0,level_1\n0,1,3\n500,2,4\n"
+        );
+        assert_eq!(
+            encode_csv(&traces[..1], 0.0).unwrap(),
+            "bin,level\n0,1\n1,2\n"
+        );
+    }
+
+    #[test]
+    fn encode_npy_shape() {
+        let npy = encode_npy(&[vec![1.0; 3], vec![2.0; 3]]).unwrap();
+        let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+        assert!(header.contains("'shape': (2, 3)"));
+        // The data is aligned to 64 bytes
+        assert_eq!((10 + header_length) % 64, 0);
+        assert_eq!(npy.len(), 10 + header_length + 6 * 4);
+    }
+
+    #[test]
+    fn reject_ragged_traces() {
+        let traces = [vec![1.0, 2.0], vec![3.0]];
+        assert!(matches!(encode_csv(&traces, 1.0), Err(Error::Export(_))));
+        assert!(matches!(encode_npy(&traces), Err(Error::Export(_))));
+    }
+}
@@ -10,18 +10,24 @@ use futuresdr::{
 
 /// Send vector of samples from a Flowgraph into a channel.
 ///
+/// Works for real-valued (`f32`, e.g., a spectrum) as well as complex-valued (`Complex32`, e.g.,
+/// for a constellation diagram) samples.
+///
 /// # Inputs
 ///
 /// `in`: Samples retrieved from the flowgraph
-pub struct ChannelSink<const FFT_SIZE: usize> {
-    tx: Sender<Box<[f32; FFT_SIZE]>>,
+pub struct ChannelSink<T, const FFT_SIZE: usize> {
+    tx: Sender<Box<[T; FFT_SIZE]>>,
 }
 
-impl<const FFT_SIZE: usize> ChannelSink<FFT_SIZE> {
-    pub fn new(tx: Sender<Box<[f32; FFT_SIZE]>>) -> Block {
+impl<T, const FFT_SIZE: usize> ChannelSink<T, FFT_SIZE>
+where
+    T: Copy + Default + Send + 'static,
+{
+    pub fn new(tx: Sender<Box<[T; FFT_SIZE]>>) -> Block {
         Block::new(
             BlockMetaBuilder::new("ChannelSink").build(),
-            StreamIoBuilder::new().add_input::<f32>("in").build(),
+            StreamIoBuilder::new().add_input::<T>("in").build(),
             MessageIoBuilder::<Self>::new().build(),
             Self { tx },
         )
@@ -30,7 +36,10 @@ impl<const FFT_SIZE: usize> ChannelSink<FFT_SIZE> {
 
 #[doc(hidden)]
 #[async_trait]
-impl<const FFT_SIZE: usize> Kernel for ChannelSink<FFT_SIZE> {
+impl<T, const FFT_SIZE: usize> Kernel for ChannelSink<T, FFT_SIZE>
+where
+    T: Copy + Default + Send + 'static,
+{
     async fn work(
         &mut self,
         io: &mut WorkIo,
@@ -38,7 +47,7 @@ impl<const FFT_SIZE: usize> Kernel for ChannelSink<FFT_SIZE> {
         _mio: &mut MessageIo<Self>,
         _meta: &mut BlockMeta,
     ) -> Result<()> {
-        let i = sio.input(0).slice::<f32>();
+        let i = sio.input(0).slice::<T>();
 
         if sio.input(0).finished() {
             io.finished = true;
@@ -46,7 +55,7 @@ impl<const FFT_SIZE: usize> Kernel for ChannelSink<FFT_SIZE> {
 
         let n = i.len() / FFT_SIZE;
         if n > 0 {
-            let mut a = [0.0; FFT_SIZE];
+            let mut a = [T::default(); FFT_SIZE];
             a.copy_from_slice(&i[(n - 1) * FFT_SIZE..n * FFT_SIZE]);
             sio.input(0).consume(n * FFT_SIZE);
             let _ = self.tx.try_send(Box::new(a));
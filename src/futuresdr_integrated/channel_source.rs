@@ -0,0 +1,82 @@
+use futuresdr::{
+    anyhow::Result,
+    futures::channel::mpsc::Receiver,
+    futures_lite::StreamExt,
+    macros::async_trait,
+    num_complex::Complex32,
+    runtime::{
+        Block, BlockMeta, BlockMetaBuilder, Kernel, MessageIo, MessageIoBuilder, StreamIo,
+        StreamIoBuilder, WorkIo,
+    },
+};
+
+/// Stream chunks of samples received from a channel into a Flowgraph, e.g., samples arriving over
+/// the network on a separate thread.
+///
+/// # Outputs
+///
+/// `out`: Samples of the chunks in the order they were received
+///
+/// The block finishes when all senders of the channel are dropped.
+pub struct ChannelSource {
+    rx: Receiver<Vec<Complex32>>,
+    /// Chunk currently being produced and the number of samples already produced from it
+    chunk: Vec<Complex32>,
+    offset: usize,
+}
+
+impl ChannelSource {
+    pub fn new(rx: Receiver<Vec<Complex32>>) -> Block {
+        Block::new(
+            BlockMetaBuilder::new("ChannelSource").build(),
+            StreamIoBuilder::new()
+                .add_output::<Complex32>("out")
+                .build(),
+            MessageIoBuilder::<Self>::new().build(),
+            Self {
+                rx,
+                chunk: Vec::new(),
+                offset: 0,
+            },
+        )
+    }
+}
+
+#[doc(hidden)]
+#[async_trait]
+impl Kernel for ChannelSource {
+    async fn work(
+        &mut self,
+        io: &mut WorkIo,
+        sio: &mut StreamIo,
+        _mio: &mut MessageIo<Self>,
+        _meta: &mut BlockMeta,
+    ) -> Result<()> {
+        let o = sio.output(0).slice::<Complex32>();
+        if o.is_empty() {
+            return Ok(());
+        }
+
+        if self.offset == self.chunk.len() {
+            // Wait for the next chunk
+            match self.rx.next().await {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.offset = 0;
+                }
+                None => {
+                    io.finished = true;
+                    return Ok(());
+                }
+            }
+        }
+
+        let n = o.len().min(self.chunk.len() - self.offset);
+        o[..n].copy_from_slice(&self.chunk[self.offset..self.offset + n]);
+        self.offset += n;
+        sio.output(0).produce(n);
+        io.call_again = true;
+
+        Ok(())
+    }
+}
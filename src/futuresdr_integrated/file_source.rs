@@ -0,0 +1,151 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use futuresdr::{
+    anyhow,
+    macros::async_trait,
+    num_complex::Complex32,
+    runtime::{
+        Block, BlockMeta, BlockMetaBuilder, Kernel, MessageIo, MessageIoBuilder, StreamIo,
+        StreamIoBuilder, WorkIo,
+    },
+};
+
+use crate::error::{Error, Result};
+
+/// Largest number of samples read from the file at once
+const MAX_CHUNK: usize = 8192;
+
+/// Converts the bytes of one sample to `Complex32`
+type DecodeFn = Box<dyn Fn(&[u8]) -> Complex32 + Send>;
+
+/// Read complex samples from a file, e.g., a raw IQ capture or the data of a WAV file.
+///
+/// # Outputs
+/// `out`: Samples converted to `Complex32`
+///
+/// The file is read as fast as the flowgraph consumes the samples, so it must be throttled to
+/// play it back in real time.
+pub struct SampleFileSource {
+    reader: BufReader<File>,
+    sample_size: usize,
+    decode: DecodeFn,
+    /// Byte offset of the first sample and number of bytes, up to the end of the file if `None`
+    start: u64,
+    len: Option<u64>,
+    /// Bytes left to read until `len` is reached
+    remaining: Option<u64>,
+    repeat: bool,
+    /// Bytes read from the file, starting with `pending` bytes of an incomplete sample
+    bytes: Vec<u8>,
+    pending: usize,
+    /// Whether anything was read since the file was (re)started, so empty files don't loop
+    read_any: bool,
+}
+
+impl SampleFileSource {
+    /// Play back `len` bytes of samples starting at byte `start` of the file at `path`. Each
+    /// sample has `sample_size` bytes and is converted by `decode`.
+    pub fn new(
+        path: &Path,
+        start: u64,
+        len: Option<u64>,
+        sample_size: usize,
+        decode: impl Fn(&[u8]) -> Complex32 + Send + 'static,
+        repeat: bool,
+    ) -> Result<Block> {
+        let mut reader = BufReader::new(File::open(path).map_err(|e| cannot_read(path, e))?);
+        reader
+            .seek(SeekFrom::Start(start))
+            .map_err(|e| cannot_read(path, e))?;
+        Ok(Block::new(
+            BlockMetaBuilder::new("SampleFileSource").build(),
+            StreamIoBuilder::new()
+                .add_output::<Complex32>("out")
+                .build(),
+            MessageIoBuilder::<Self>::new().build(),
+            Self {
+                reader,
+                sample_size,
+                decode: Box::new(decode),
+                start,
+                len,
+                remaining: len,
+                repeat,
+                bytes: Vec::new(),
+                pending: 0,
+                read_any: false,
+            },
+        ))
+    }
+}
+
+pub fn cannot_read(path: &Path, e: std::io::Error) -> Error {
+    Error::Source(format!("cannot read {}: {e}", path.display()))
+}
+
+#[doc(hidden)]
+#[async_trait]
+impl Kernel for SampleFileSource {
+    async fn work(
+        &mut self,
+        io: &mut WorkIo,
+        sio: &mut StreamIo,
+        _mio: &mut MessageIo<Self>,
+        _meta: &mut BlockMeta,
+    ) -> anyhow::Result<()> {
+        let o = sio.output(0).slice::<Complex32>();
+        if o.is_empty() {
+            return Ok(());
+        }
+
+        let size = self.sample_size;
+        let mut end = o.len().min(MAX_CHUNK) * size;
+        if let Some(remaining) = self.remaining {
+            end = end.min(self.pending + remaining.min(end as u64) as usize);
+        }
+        self.bytes.resize(end.max(self.pending), 0);
+        let read = if end > self.pending {
+            self.reader.read(&mut self.bytes[self.pending..end])?
+        } else {
+            0
+        };
+
+        if read == 0 {
+            // End of the samples, an incomplete last sample is dropped
+            if self.repeat && self.read_any {
+                self.reader.seek(SeekFrom::Start(self.start))?;
+                self.remaining = self.len;
+                self.pending = 0;
+                self.read_any = false;
+                io.call_again = true;
+            } else {
+                io.finished = true;
+            }
+            return Ok(());
+        }
+        self.read_any = true;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= read as u64;
+        }
+
+        let available = self.pending + read;
+        let m = available / size;
+        for (r, sample) in o.iter_mut().zip(self.bytes[..m * size].chunks_exact(size)) {
+            *r = (self.decode)(sample);
+        }
+        self.bytes.copy_within(m * size..available, 0);
+        self.pending = available - m * size;
+
+        if m > 0 {
+            sio.output(0).produce(m);
+        } else {
+            io.call_again = true;
+        }
+
+        Ok(())
+    }
+}
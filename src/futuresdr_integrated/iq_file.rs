@@ -0,0 +1,231 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use futuresdr::{num_complex::Complex32, runtime::Block};
+
+use super::file_source::{cannot_read, SampleFileSource};
+use crate::{
+    error::{Error, Result},
+    MainWindow,
+};
+
+/// Sample formats of raw IQ files, interleaved I and Q in little-endian byte order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IqFormat {
+    /// 32 bit floats, e.g., written by GNU Radio or FutureSDR
+    Cf32,
+    /// 16 bit signed integers, e.g., written by many SDR tools
+    Ci16,
+    /// 8 bit unsigned integers offset by 127.5, as written by `rtl_sdr`
+    Cu8,
+}
+
+impl IqFormat {
+    /// Bytes per complex sample
+    pub fn sample_size(self) -> usize {
+        match self {
+            Self::Cf32 => 8,
+            Self::Ci16 => 4,
+            Self::Cu8 => 2,
+        }
+    }
+
+    /// Convert one sample of [`IqFormat::sample_size`] bytes, integers are scaled to `-1.0..1.0`
+    pub fn decode(self, bytes: &[u8]) -> Complex32 {
+        match self {
+            Self::Cf32 => Complex32::new(
+                f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                f32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            ),
+            Self::Ci16 => Complex32::new(
+                i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+                i16::from_le_bytes([bytes[2], bytes[3]]) as f32 / 32768.0,
+            ),
+            Self::Cu8 => Complex32::new(
+                (bytes[0] as f32 - 127.5) / 127.5,
+                (bytes[1] as f32 - 127.5) / 127.5,
+            ),
+        }
+    }
+
+    /// Append `sample` to `bytes`, the inverse of [`IqFormat::decode`]. Integers saturate outside
+    /// of `-1.0..1.0`.
+    pub fn encode(self, sample: Complex32, bytes: &mut Vec<u8>) {
+        for x in [sample.re, sample.im] {
+            match self {
+                Self::Cf32 => bytes.extend_from_slice(&x.to_le_bytes()),
+                Self::Ci16 => bytes.extend_from_slice(&((x * 32768.0) as i16).to_le_bytes()),
+                Self::Cu8 => bytes.push((x * 127.5 + 127.5).round() as u8),
+            }
+        }
+    }
+
+    /// Format of a SigMF `core:datatype`, only little-endian complex types are supported
+    fn from_sigmf(datatype: &str) -> Option<Self> {
+        match datatype {
+            "cf32_le" => Some(Self::Cf32),
+            "ci16_le" => Some(Self::Ci16),
+            "cu8" | "cu8_le" => Some(Self::Cu8),
+            _ => None,
+        }
+    }
+}
+
+/// An IQ capture to play back
+#[derive(Clone, Debug, PartialEq)]
+pub struct IqFile {
+    pub path: PathBuf,
+    pub format: IqFormat,
+    /// Samples per second, the file is played back at this rate
+    pub sample_rate: f64,
+    /// Start over at the end of the file instead of stopping
+    pub repeat: bool,
+}
+
+impl IqFile {
+    /// File at `path` with the settings of the Source tab of the GUI. For SigMF recordings, the
+    /// format and sample rate are read from the metadata.
+    pub fn from_app(app: &MainWindow, path: &Path) -> Result<Self> {
+        let path = path.to_path_buf();
+        let sample_rate = app.get_source_sample_rate() as f64 * 1000.0;
+        let repeat = app.get_source_loop();
+        // Same order as in the GUI
+        let format = match app.get_iq_format() {
+            0 => IqFormat::Cf32,
+            1 => IqFormat::Ci16,
+            2 => IqFormat::Cu8,
+            _ => return Self::sigmf(&path, repeat),
+        };
+        Ok(Self {
+            path,
+            format,
+            sample_rate,
+            repeat,
+        })
+    }
+
+    /// SigMF recording, given by either its `.sigmf-meta` or its `.sigmf-data` file
+    pub fn sigmf(path: &Path, repeat: bool) -> Result<Self> {
+        let meta_path = path.with_extension("sigmf-meta");
+        let meta = fs::read_to_string(&meta_path).map_err(|e| cannot_read(&meta_path, e))?;
+        let invalid =
+            |what: &str| Error::Source(format!("{what} in SigMF metadata {}", meta_path.display()));
+
+        let datatype = json_value(&meta, "core:datatype").ok_or_else(|| invalid("no datatype"))?;
+        let format = IqFormat::from_sigmf(datatype)
+            .ok_or_else(|| invalid(&format!("unsupported datatype {datatype}")))?;
+        let sample_rate = json_value(&meta, "core:sample_rate")
+            .and_then(|rate| rate.parse().ok())
+            .filter(|rate: &f64| *rate > 0.0)
+            .ok_or_else(|| invalid("no valid sample rate"))?;
+
+        Ok(Self {
+            path: path.with_extension("sigmf-data"),
+            format,
+            sample_rate,
+            repeat,
+        })
+    }
+
+    /// Block playing back the whole file, see [`SampleFileSource`]
+    pub fn source(&self) -> Result<Block> {
+        let format = self.format;
+        SampleFileSource::new(
+            &self.path,
+            0,
+            None,
+            format.sample_size(),
+            move |sample| format.decode(sample),
+            self.repeat,
+        )
+    }
+}
+
+/// Value of the first `"key": value` pair in `json`, without the quotes of strings. This is enough
+/// for the global fields of SigMF metadata, which are neither nested nor escaped.
+fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let quoted_key = format!("\"{key}\"");
+    let rest = &json[json.find(&quoted_key)? + quoted_key.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    match rest.strip_prefix('"') {
+        Some(string) => string.split('"').next(),
+        None => rest
+            .split(|c: char| c == ',' || c == '}' || c.is_whitespace())
+            .next(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMATS: [IqFormat; 3] = [IqFormat::Cf32, IqFormat::Ci16, IqFormat::Cu8];
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let samples = [
+            Complex32::new(0.0, 0.0),
+            Complex32::new(0.5, -0.5),
+            Complex32::new(-1.0, 0.99),
+        ];
+        for format in FORMATS {
+            for sample in samples {
+                let mut bytes = Vec::new();
+                format.encode(sample, &mut bytes);
+                assert_eq!(bytes.len(), format.sample_size());
+                // Within half a step of the integer formats
+                let decoded = format.decode(&bytes);
+                assert!((decoded - sample).norm() < 0.006, "{format:?}: {decoded}");
+            }
+        }
+    }
+
+    #[test]
+    fn decode_encode_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for format in FORMATS {
+            for sample in bytes.chunks_exact(format.sample_size()) {
+                let mut encoded = Vec::new();
+                format.encode(format.decode(sample), &mut encoded);
+                assert_eq!(encoded, sample, "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn saturate_integers() {
+        let mut bytes = Vec::new();
+        IqFormat::Ci16.encode(Complex32::new(2.0, -2.0), &mut bytes);
+        IqFormat::Cu8.encode(Complex32::new(2.0, -2.0), &mut bytes);
+        assert_eq!(bytes, [0xff, 0x7f, 0x00, 0x80, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn read_sigmf_values() {
+        let meta = r#"{
+            "global": {
+                "core:datatype": "ci16_le",
+                "core:sample_rate":2400000.0,
+                "core:version": "1.0.0"
+            },
+            "captures": [{"core:sample_start": 0}]
+        }"#;
+        assert_eq!(json_value(meta, "core:datatype"), Some("ci16_le"));
+        assert_eq!(json_value(meta, "core:sample_rate"), Some("2400000.0"));
+        assert_eq!(json_value(meta, "core:sample_start"), Some("0"));
+        assert_eq!(json_value(r#"{"core:hw": "x"}"#, "core:hw"), Some("x"));
+        assert_eq!(json_value(r#"{"core:hw": ""}"#, "core:hw"), Some(""));
+    }
+
+    #[test]
+    fn reject_missing_sigmf_values() {
+        assert_eq!(json_value("{}", "core:datatype"), None);
+        // A key without a value, e.g., in a truncated file
+        assert_eq!(json_value(r#"{"core:datatype""#, "core:datatype"), None);
+        assert_eq!(json_value(r#"["core:datatype", 1]"#, "core:datatype"), None);
+        assert_eq!(IqFormat::from_sigmf("cf32_be"), None);
+        assert_eq!(IqFormat::from_sigmf("cu8"), Some(IqFormat::Cu8));
+    }
+}
@@ -1,13 +1,19 @@
 use futuresdr::{
-    anyhow::Result, blocks::Apply, blocks::Fft, blocks::FftDirection, blocks::SignalSourceBuilder,
-    blocks::Throttle, futures::channel::mpsc::channel, futures_lite::StreamExt, macros::connect,
-    num_complex::Complex32, runtime::buffer::slab::Slab, runtime::Flowgraph, runtime::Pmt,
-    runtime::Runtime,
+    blocks::Apply, blocks::Fft, blocks::FftDirection, blocks::SignalSourceBuilder,
+    blocks::Throttle, futures::channel::mpsc::channel, futures::channel::mpsc::Receiver,
+    futures_lite::StreamExt, macros::connect, num_complex::Complex32, runtime::buffer::slab::Slab,
+    runtime::Block, runtime::Flowgraph, runtime::Pmt, runtime::Runtime,
 };
 use log::{debug, info};
-use slint::Weak;
+use slint::{ComponentHandle, Weak};
+use std::path::{Path, PathBuf};
 
-use crate::{rendering::render_plot, MainWindow, FFT_SIZE, PLOT_RATE};
+use crate::{
+    error::{self, Error, Result},
+    rendering::{channels, constellation::Constellation, render_plot_channels},
+    supervisor::SourceHandle,
+    MainWindow, PlotMeta, FFT_SIZE, PLOT_RATE,
+};
 
 mod channel_sink;
 use channel_sink::ChannelSink;
@@ -15,81 +21,584 @@ use channel_sink::ChannelSink;
 mod additive_noise;
 use additive_noise::AdditiveNoise;
 
-pub async fn wait_for_samples(window_weak: Weak<MainWindow>) -> Result<()> {
-    // Store the noise value locally, so we only send a change message to the FG if it really changed
-    let mut noise_val = get_noise(&window_weak.clone().unwrap());
+mod tee;
+use tee::Tee;
 
-    let mut fg = Flowgraph::new();
+mod file_source;
 
-    // Set it to some arbitrary frequency
-    let src =
-        SignalSourceBuilder::<Complex32>::sin(PLOT_RATE as f32 / 4.0, PLOT_RATE as f32).build();
+mod iq_file;
+use iq_file::IqFile;
 
-    // Additive Noise
-    let noise = AdditiveNoise::new(noise_val);
+mod wav_file;
+use wav_file::WavFile;
 
-    // Store the `gain` port ID for later use
-    let gain_message_id = noise
-        .message_input_name_to_id("gain")
-        .expect("No gain message id found!");
+#[cfg(not(target_arch = "wasm32"))]
+mod channel_source;
 
-    // FFT Block
-    let fft = Fft::with_options(FFT_SIZE, FftDirection::Forward, false, None);
+#[cfg(not(target_arch = "wasm32"))]
+mod network;
+#[cfg(not(target_arch = "wasm32"))]
+use network::{show_connection, NetworkStream, ReceiveThread, SendThread};
 
-    // Throttle sample rate to allow the GUI to render the plot in the meantime
-    let throttle = Throttle::<Complex32>::new(PLOT_RATE as f64);
+#[cfg(not(target_arch = "wasm32"))]
+mod rtl_tcp;
+#[cfg(not(target_arch = "wasm32"))]
+use rtl_tcp::{RtlTcp, RtlTcpConfig};
 
-    // We don't want to plot complex values, so take the absolute value
-    let mag = Apply::new(|x: &Complex32| x.norm());
+#[cfg(not(target_arch = "wasm32"))]
+use crate::ConnectionState;
 
-    // Create channel for the channel sink
-    let (set_samples, mut samples) = channel::<Box<[f32; FFT_SIZE]>>(10);
-    let snk = ChannelSink::new(set_samples);
+/// Run the flowgraph and plot its output until `source` is cancelled
+pub async fn wait_for_samples(window_weak: Weak<MainWindow>, source: SourceHandle) -> Result<()> {
+    let Some(app) = window_weak.upgrade() else {
+        return Ok(());
+    };
+    // Store the noise value locally, so we only send a change message to the FG if it really changed
+    let mut noise_val = get_noise(&app);
+    let sample_source = SampleSource::from_app(&app)?;
+    let sample_rate = sample_source.sample_rate();
 
-    // Connect with custom buffers to get output in real-time.
-    // Use 8*FFT_SIZE for Complex32 (2x 4 bytes) and 4*FFT_SIZE for f32 (1x 4 bytes)
-    connect!(fg, src  > noise  [Slab::with_config(8 * FFT_SIZE, 2, 0)] fft [Slab::with_config(8 * FFT_SIZE, 2, 0)] throttle [Slab::with_config(4 * FFT_SIZE, 2, 0)] mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] snk);
+    // The spectrum of real samples is symmetric, so only the non-negative frequencies up to half
+    // the sample rate are plotted, e.g., for audio. Otherwise the bins span the whole sample rate.
+    let (bins, span) = if sample_source.is_real() {
+        (FFT_SIZE / 2, sample_rate / 2.0)
+    } else {
+        (FFT_SIZE, sample_rate)
+    };
+    app.global::<PlotMeta>().set_sample_rate(span as f32);
+    channels::define(&app, &sample_source.channel_names());
+    #[cfg(not(target_arch = "wasm32"))]
+    let output = NetworkStream::output_from_app(&app);
+    drop(app);
+
+    // Live sources start receiving first. Their thread is stopped when its handle is dropped, the
+    // rtl_tcp client is kept to change the settings of the dongle.
+    #[cfg(not(target_arch = "wasm32"))]
+    let (mut receiving, mut rtl_tcp) = (None, None);
+    let mut files = sample_source.file_sources()?.into_iter();
+    let src = match &sample_source {
+        #[cfg(not(target_arch = "wasm32"))]
+        SampleSource::RtlTcp(config) => {
+            let Some(connected) = connect_rtl_tcp(&window_weak, &source, config.clone()).await
+            else {
+                return Ok(());
+            };
+            let (client, thread, block) = connected?;
+            rtl_tcp = Some(client);
+            receiving = Some(thread);
+            Some(block)
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        SampleSource::Network { stream, .. } => {
+            let (thread, block) = stream.receive(window_weak.clone())?;
+            receiving = Some(thread);
+            Some(block)
+        }
+        _ => files.next(),
+    };
+    // Further files are plotted as channels of their own
+    let others: Vec<Block> = files.collect();
+
+    // Optionally send the time-domain samples over the network as well
+    #[cfg(not(target_arch = "wasm32"))]
+    let (sending, output) = match output {
+        Some(stream) => {
+            let (thread, out) = stream.send()?;
+            (Some(thread), Some(out))
+        }
+        None => (None, None),
+    };
+    #[cfg(target_arch = "wasm32")]
+    let output = None;
+
+    let (
+        fg,
+        Sinks {
+            mut samples,
+            mut symbols,
+            mut channels,
+            noise,
+        },
+    ) = build_flowgraph(src, others, &sample_source, noise_val, output)?;
+    let mut constellation = Constellation::new();
+    let mut latest: Vec<Option<Box<[f32; FFT_SIZE]>>> = vec![None; channels.len()];
 
     info!("Start FG");
     let rt = Runtime::new();
     let (_, mut fg) = rt.start(fg).await;
+    source.set_running();
 
-    // Processing loop for samples in the channel
-    while let Some(samples) = samples.next().await {
-        // Only do the (expensive) rendering operation when plotting is enabled
-        if window_weak.clone().unwrap().get_plot_enable() {
+    // Processing loop for samples in the channel, stops as soon as the source is cancelled or
+    // fails. The flowgraph is terminated in either case.
+    let result = async {
+        while let Some(Some(samples)) = source.until_cancelled(samples.next()).await {
             debug!("updating");
+            let Some(app) = window_weak.upgrade() else {
+                break;
+            };
+
+            // Plot the other channels next to the most recent spectrum of the first one
+            for (receiver, latest) in channels.iter_mut().zip(&mut latest) {
+                while let Ok(frame) = receiver.try_recv() {
+                    *latest = Some(frame);
+                }
+            }
+            let others = latest.clone();
 
             let window = window_weak.clone();
             slint::invoke_from_event_loop(move || {
-                let app = window.unwrap();
-                app.set_plot_frame(render_plot(samples.as_ref(), &app));
-            })
-            .expect("Start rendering");
+                let Some(app) = window.upgrade() else { return };
+                // Channels without a spectrum yet stay empty, so that the others keep their index
+                let mut frames = vec![&samples[..bins]];
+                frames.extend(
+                    others
+                        .iter()
+                        .map(|frame| frame.as_ref().map_or(&[][..], |frame| &frame[..bins])),
+                );
+                if let Err(e) = render_plot_channels(&frames, &app) {
+                    error::report(&app, &e);
+                }
+            })?;
+
+            // Only keep the most recent block of symbols, older ones would be dropped anyway
+            let mut latest_symbols = None;
+            while let Ok(s) = symbols.try_recv() {
+                latest_symbols = Some(s);
+            }
+            if let Some(s) = latest_symbols {
+                // We are running on the event loop already, so we can render in place
+                if app.get_constellation_enable() {
+                    let points: Vec<(f32, f32)> = s.iter().map(|c| (c.re, c.im)).collect();
+                    constellation.push(&points, &app);
+                    if let Err(e) = constellation.render(&app) {
+                        error::report(&app, &e);
+                    }
+                } else {
+                    constellation.clear();
+                }
+            }
 
             // Obtain the noise level from the UI
-            let noise_val_new = get_noise(&window_weak.clone().unwrap());
+            let noise_val_new = get_noise(&app);
             // Update only when it has changed to avoid potentially expensive message call to FG
-            if noise_val_new != noise_val {
-                noise_val = noise_val_new;
-                info!("Setting noise to {noise_val}");
-                fg.call(noise, gain_message_id, Pmt::F32(noise_val))
-                    .await
-                    .unwrap()
+            if let Some((noise, gain_message_id)) = noise {
+                if noise_val_new != noise_val {
+                    noise_val = noise_val_new;
+                    info!("Setting noise to {noise_val}");
+                    fg.call(noise, gain_message_id, Pmt::F32(noise_val))
+                        .await
+                        .map_err(|e| Error::Source(format!("cannot set noise: {e}")))?;
+                }
+            }
+
+            // Tune the dongle, only changes are sent to the server
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(client) = &mut rtl_tcp {
+                client.update(&RtlTcpConfig::from_app(&app))?;
+            }
+
+            // Samples are dropped if they cannot be sent, so the output fails the source
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(e) = sending.as_ref().and_then(SendThread::error) {
+                return Err(Error::Source(e));
             }
-        } else {
-            // If the plot is no longer enabled we break the loop so the FT can be terminated
-            break;
         }
+        Ok::<_, Error>(())
     }
+    .await;
+
+    // Stop receiving, a lost connection fails the source so that it can be retried
+    #[cfg(not(target_arch = "wasm32"))]
+    let result = match receiving.take() {
+        Some(thread) => {
+            let lost = thread.error();
+            drop(thread);
+            show_connection(&window_weak, ConnectionState::None, String::new());
+            match (result, lost) {
+                (Ok(()), Some(e)) if !source.is_cancelled() => Err(Error::Source(e)),
+                (result, _) => result,
+            }
+        }
+        None => result,
+    };
 
     info!("Terminate FG");
-    fg.terminate_and_wait().await.expect("Terminate Flowgraph");
+    fg.terminate_and_wait()
+        .await
+        .map_err(|e| Error::Source(format!("cannot terminate flowgraph: {e}")))?;
     info!("FG terminated");
-    Ok(())
+    result
+}
+
+/// Receiving ends of the sinks of the flowgraph and the blocks that are controlled while it runs
+struct Sinks {
+    /// Magnitude spectra of the time-domain samples
+    samples: Receiver<Box<[f32; FFT_SIZE]>>,
+    /// Complex samples shown in the constellation diagram
+    symbols: Receiver<Box<[Complex32; FFT_SIZE]>>,
+    /// Spectra of the further channels: the synthetic tone before the noise is added, or the
+    /// files after the first one
+    channels: Vec<Receiver<Box<[f32; FFT_SIZE]>>>,
+    /// Noise block of the synthetic source and the ID of its `gain` port
+    noise: Option<(usize, usize)>,
+}
+
+/// Flowgraph from the time-domain samples of `src` to the sinks, the synthetic source is generated
+/// if `src` is `None`. The time-domain samples are sent to `output` as well, if given. Each of the
+/// `others` is played back as a further channel.
+fn build_flowgraph(
+    src: Option<Block>,
+    others: Vec<Block>,
+    sample_source: &SampleSource,
+    noise_val: f32,
+    output: Option<Block>,
+) -> Result<(Flowgraph, Sinks)> {
+    let mut fg = Flowgraph::new();
+
+    // FFT Block
+    let fft = Fft::with_options(FFT_SIZE, FftDirection::Forward, false, None);
+
+    // Throttle sample rate to allow the GUI to render the plot in the meantime
+    let throttle = Throttle::<Complex32>::new(sample_source.sample_rate());
+
+    // We don't want to plot complex values, so take the absolute value
+    let mag = Apply::new(|x: &Complex32| x.norm());
+
+    // Create channel for the channel sink
+    let (set_samples, samples) = channel::<Box<[f32; FFT_SIZE]>>(10);
+    let snk = ChannelSink::new(set_samples);
+
+    // Second channel sink for the complex samples shown in the constellation diagram
+    let (set_symbols, symbols) = channel::<Box<[Complex32; FFT_SIZE]>>(10);
+    let symbol_snk = ChannelSink::new(set_symbols);
+
+    let mut channels = Vec::new();
+
+    // The time-domain samples feed the FFT, the constellation diagram and the optional output
+    let tee = Tee::<Complex32>::new(2 + output.is_some() as usize);
+
+    // Connect with custom buffers to get output in real-time.
+    // Use 8*FFT_SIZE for Complex32 (2x 4 bytes) and 4*FFT_SIZE for f32 (1x 4 bytes)
+    // The constellation diagram taps the time-domain signal before the FFT.
+    // Only the synthetic source has a noise block, whose `gain` port ID is stored for later use.
+    // Live sources already deliver the samples in real time, so they are not throttled.
+    let (tee, noise) = match src {
+        None => {
+            // Set it to some arbitrary frequency
+            let src =
+                SignalSourceBuilder::<Complex32>::sin(PLOT_RATE as f32 / 4.0, PLOT_RATE as f32)
+                    .build();
+
+            // Additive Noise
+            let noise = AdditiveNoise::new(noise_val);
+            let gain_message_id = noise
+                .message_input_name_to_id("gain")
+                .ok_or_else(|| Error::Source("no gain message input found".to_string()))?;
+
+            // The tone is copied for the clean branch, which runs at the pace of the throttled
+            // noisy one
+            let tone = Tee::<Complex32>::new(2);
+            connect!(fg, src [Slab::with_config(8 * FFT_SIZE, 2, 0)] tone);
+            connect!(fg, tone.out0 [Slab::with_config(8 * FFT_SIZE, 2, 0)] noise [Slab::with_config(8 * FFT_SIZE, 2, 0)] tee);
+            connect!(fg, tee.out0 [Slab::with_config(8 * FFT_SIZE, 2, 0)] fft [Slab::with_config(8 * FFT_SIZE, 2, 0)] throttle [Slab::with_config(4 * FFT_SIZE, 2, 0)] mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] snk);
+            connect!(fg, tee.out1 [Slab::with_config(8 * FFT_SIZE, 2, 0)] symbol_snk);
+
+            // Spectrum of the tone before the noise is added, plotted as second channel
+            let (set_clean, clean) = channel::<Box<[f32; FFT_SIZE]>>(10);
+            let clean_snk = ChannelSink::new(set_clean);
+            channels.push(clean);
+            let clean_fft = Fft::with_options(FFT_SIZE, FftDirection::Forward, false, None);
+            let clean_mag = Apply::new(|x: &Complex32| x.norm());
+            connect!(fg, tone.out1 [Slab::with_config(8 * FFT_SIZE, 2, 0)] clean_fft [Slab::with_config(4 * FFT_SIZE, 2, 0)] clean_mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] clean_snk);
+            (tee, Some((noise, gain_message_id)))
+        }
+        Some(src) if sample_source.is_live() => {
+            connect!(fg, src [Slab::with_config(8 * FFT_SIZE, 2, 0)] tee);
+            connect!(fg, tee.out0 [Slab::with_config(8 * FFT_SIZE, 2, 0)] fft [Slab::with_config(4 * FFT_SIZE, 2, 0)] mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] snk);
+            connect!(fg, tee.out1 [Slab::with_config(8 * FFT_SIZE, 2, 0)] symbol_snk);
+            (tee, None)
+        }
+        Some(src) => {
+            connect!(fg, src [Slab::with_config(8 * FFT_SIZE, 2, 0)] tee);
+            connect!(fg, tee.out0 [Slab::with_config(8 * FFT_SIZE, 2, 0)] fft [Slab::with_config(8 * FFT_SIZE, 2, 0)] throttle [Slab::with_config(4 * FFT_SIZE, 2, 0)] mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] snk);
+            connect!(fg, tee.out1 [Slab::with_config(8 * FFT_SIZE, 2, 0)] symbol_snk);
+            (tee, None)
+        }
+    };
+
+    if let Some(out) = output {
+        connect!(fg, tee.out2 [Slab::with_config(8 * FFT_SIZE, 2, 0)] out);
+    }
+
+    // The other files are throttled on their own, so that they play back at the same pace
+    for src in others {
+        let (set_spectrum, spectrum) = channel::<Box<[f32; FFT_SIZE]>>(10);
+        let snk = ChannelSink::new(set_spectrum);
+        channels.push(spectrum);
+        let fft = Fft::with_options(FFT_SIZE, FftDirection::Forward, false, None);
+        let throttle = Throttle::<Complex32>::new(sample_source.sample_rate());
+        let mag = Apply::new(|x: &Complex32| x.norm());
+        connect!(fg, src [Slab::with_config(8 * FFT_SIZE, 2, 0)] fft [Slab::with_config(8 * FFT_SIZE, 2, 0)] throttle [Slab::with_config(4 * FFT_SIZE, 2, 0)] mag [Slab::with_config(4 * FFT_SIZE, 2, 0)] snk);
+    }
+
+    Ok((
+        fg,
+        Sinks {
+            samples,
+            symbols,
+            channels,
+            noise,
+        },
+    ))
+}
+
+/// Where the time-domain samples of the flowgraph come from
+enum SampleSource {
+    /// A tone with adjustable noise
+    Synthetic,
+    /// Playback of one or more captures, each plotted as a channel of its own. There is at least
+    /// one file, the first one is the live channel.
+    IqFiles(Vec<IqFile>),
+    /// Playback of one or more audio recordings, like [`SampleSource::IqFiles`]
+    WavFiles(Vec<WavFile>),
+    /// Live samples of an rtl_tcp server
+    #[cfg(not(target_arch = "wasm32"))]
+    RtlTcp(RtlTcpConfig),
+    /// Live samples of a raw IQ stream, e.g., from GNU Radio
+    #[cfg(not(target_arch = "wasm32"))]
+    Network {
+        stream: NetworkStream,
+        sample_rate: f64,
+    },
+}
+
+impl SampleSource {
+    /// Source selected in the GUI, only the synthetic one is available without file access
+    fn from_app(app: &MainWindow) -> Result<Self> {
+        // Same order as in the GUI
+        match app.get_source_kind() {
+            1 if app.get_file_access() => Self::IqFiles(
+                source_paths(app)?
+                    .iter()
+                    .map(|path| IqFile::from_app(app, path))
+                    .collect::<Result<_>>()?,
+            )
+            .check_files(),
+            2 if app.get_file_access() => Self::WavFiles(
+                source_paths(app)?
+                    .iter()
+                    .map(|path| WavFile::from_app(app, path))
+                    .collect::<Result<_>>()?,
+            )
+            .check_files(),
+            #[cfg(not(target_arch = "wasm32"))]
+            3 => Ok(Self::RtlTcp(RtlTcpConfig::from_app(app))),
+            #[cfg(not(target_arch = "wasm32"))]
+            4 => Ok(Self::Network {
+                stream: NetworkStream::input_from_app(app),
+                sample_rate: app.get_source_sample_rate() as f64 * 1000.0,
+            }),
+            _ => Ok(Self::Synthetic),
+        }
+    }
+
+    /// Samples per second
+    fn sample_rate(&self) -> f64 {
+        match self {
+            Self::Synthetic => PLOT_RATE as f64,
+            Self::IqFiles(files) => files[0].sample_rate,
+            Self::WavFiles(files) => files[0].sample_rate as f64,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::RtlTcp(config) => config.sample_rate as f64,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Network { sample_rate, .. } => *sample_rate,
+        }
+    }
+
+    /// Whether the samples arrive in real time instead of being generated or read on demand
+    fn is_live(&self) -> bool {
+        match self {
+            Self::Synthetic | Self::IqFiles(_) | Self::WavFiles(_) => false,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::RtlTcp(_) | Self::Network { .. } => true,
+        }
+    }
+
+    /// Names of the plotted channels, the synthetic source also plots the tone without noise
+    fn channel_names(&self) -> Vec<String> {
+        let file_name = |path: &Path| {
+            path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into(),
+            )
+        };
+        match self {
+            Self::Synthetic => vec!["Tone + Noise".into(), "Clean Tone".into()],
+            Self::IqFiles(files) => files.iter().map(|file| file_name(&file.path)).collect(),
+            Self::WavFiles(files) => files.iter().map(|file| file_name(&file.path)).collect(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::RtlTcp(_) | Self::Network { .. } => vec!["Spectrum".into()],
+        }
+    }
+
+    /// Whether the samples only have a real part
+    fn is_real(&self) -> bool {
+        match self {
+            Self::Synthetic | Self::IqFiles(_) => false,
+            Self::WavFiles(files) => files[0].is_real(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::RtlTcp(_) | Self::Network { .. } => false,
+        }
+    }
+
+    /// Blocks reading the samples of file sources, one per file and none for the synthetic and
+    /// live sources
+    fn file_sources(&self) -> Result<Vec<Block>> {
+        let play = |path: &Path, source: Result<Block>| {
+            info!("Playing back {}", path.display());
+            source
+        };
+        match self {
+            Self::Synthetic => Ok(Vec::new()),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::RtlTcp(_) | Self::Network { .. } => Ok(Vec::new()),
+            Self::IqFiles(files) => files
+                .iter()
+                .map(|file| play(&file.path, file.source()))
+                .collect(),
+            Self::WavFiles(files) => files
+                .iter()
+                .map(|file| play(&file.path, file.source()))
+                .collect(),
+        }
+    }
+
+    /// The files are plotted on the same frequency axis, so they need the same sample rate and
+    /// must either all be real or all be complex
+    fn check_files(self) -> Result<Self> {
+        let formats: Vec<(f64, bool)> = match &self {
+            Self::IqFiles(files) => files.iter().map(|file| (file.sample_rate, false)).collect(),
+            Self::WavFiles(files) => files
+                .iter()
+                .map(|file| (file.sample_rate as f64, file.is_real()))
+                .collect(),
+            _ => return Ok(self),
+        };
+        if formats.windows(2).any(|pair| pair[0] != pair[1]) {
+            return Err(Error::Source(
+                "all files must have the same sample rate and number of channels".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Files to play back, several are separated by `;` and plotted as channels of their own
+fn source_paths(app: &MainWindow) -> Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = app
+        .get_source_path()
+        .split(';')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect();
+    if paths.is_empty() {
+        return Err(Error::Source("no file given".to_string()));
+    }
+    Ok(paths)
+}
+
+/// Connect to the rtl_tcp server of `config` and show the progress, `None` if `source` was
+/// cancelled in the meantime
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect_rtl_tcp(
+    window_weak: &Weak<MainWindow>,
+    source: &SourceHandle,
+    config: RtlTcpConfig,
+) -> Option<Result<(RtlTcp, ReceiveThread, Block)>> {
+    let address = config.address.clone();
+    show_connection(window_weak, ConnectionState::Connecting, address.clone());
+    let connected = source.until_cancelled(RtlTcp::connect(config)).await;
+    match &connected {
+        Some(Ok((client, ..))) => show_connection(
+            window_weak,
+            ConnectionState::Connected,
+            format!(
+                "{address}, {} tuner with {} gain steps",
+                client.info.tuner_name(),
+                client.info.gain_count
+            ),
+        ),
+        Some(Err(_)) => show_connection(window_weak, ConnectionState::Disconnected, address),
+        None => show_connection(window_weak, ConnectionState::None, String::new()),
+    }
+    connected
 }
 
 /// Noise SpinBox on the GUI including conversion and scaling
 fn get_noise(window: &MainWindow) -> f32 {
     window.get_noise() as f32 / 10.0
 }
+
+#[cfg(test)]
+mod tests {
+    use futuresdr::async_io::block_on;
+
+    use super::*;
+
+    /// Start the flowgraph and wait for a block of samples of each sink, which fails if a buffer
+    /// has more readers than it supports
+    #[test]
+    fn played_back_flowgraph_runs() {
+        // A tone stands in for the samples of a file
+        let tone =
+            SignalSourceBuilder::<Complex32>::sin(PLOT_RATE as f32 / 4.0, PLOT_RATE as f32).build();
+        let (fg, mut sinks) =
+            build_flowgraph(Some(tone), Vec::new(), &SampleSource::Synthetic, 0.0, None).unwrap();
+        block_on(async {
+            let (_, mut fg) = Runtime::new().start(fg).await;
+            assert!(sinks.samples.next().await.is_some());
+            assert!(sinks.symbols.next().await.is_some());
+            fg.terminate_and_wait().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn played_back_files_run() {
+        let tone = |frequency: f32| {
+            SignalSourceBuilder::<Complex32>::sin(frequency, PLOT_RATE as f32).build()
+        };
+        let others = vec![tone(1000.0), tone(2000.0)];
+        let (fg, mut sinks) = build_flowgraph(
+            Some(tone(500.0)),
+            others,
+            &SampleSource::Synthetic,
+            0.0,
+            None,
+        )
+        .unwrap();
+        assert_eq!(sinks.channels.len(), 2);
+        block_on(async {
+            let (_, mut fg) = Runtime::new().start(fg).await;
+            assert!(sinks.samples.next().await.is_some());
+            for channel in &mut sinks.channels {
+                assert!(channel.next().await.is_some());
+            }
+            fg.terminate_and_wait().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn synthetic_flowgraph_runs() {
+        let (fg, mut sinks) =
+            build_flowgraph(None, Vec::new(), &SampleSource::Synthetic, 0.5, None).unwrap();
+        block_on(async {
+            let (_, mut fg) = Runtime::new().start(fg).await;
+            assert!(sinks.samples.next().await.is_some());
+            assert!(sinks.symbols.next().await.is_some());
+            assert_eq!(sinks.channels.len(), 1);
+            assert!(sinks.channels[0].next().await.is_some());
+            fg.terminate_and_wait().await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,562 @@
+//! Raw IQ streams over TCP and UDP, compatible with the TCP and UDP blocks of GNU Radio.
+//!
+//! The streams carry interleaved little-endian samples without any header or framing, so format
+//! and sample rate must be configured the same way on both ends. Sockets block, so they are served
+//! by separate threads, which exchange the samples with the flowgraph through channels.
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use futuresdr::{
+    anyhow,
+    futures::channel::mpsc,
+    macros::async_trait,
+    num_complex::Complex32,
+    runtime::{
+        Block, BlockMeta, BlockMetaBuilder, Kernel, MessageIo, MessageIoBuilder, StreamIo,
+        StreamIoBuilder, WorkIo,
+    },
+};
+use log::{debug, info};
+use slint::Weak;
+
+use super::{channel_source::ChannelSource, iq_file::IqFormat};
+use crate::{
+    error::{Error, Result},
+    ConnectionState, MainWindow,
+};
+
+/// Time to wait for a server to accept a connection
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often threads check whether they should stop while nothing arrives
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Bytes received at once, large enough for any UDP datagram
+const CHUNK_SIZE: usize = 65536;
+/// Chunks buffered between the threads and the flowgraph. Further chunks are dropped, so the plot
+/// doesn't lag behind if either side cannot keep up.
+const CHUNK_BUFFER: usize = 16;
+/// Largest UDP payload sent, the default of GNU Radio's UDP Sink to fit into an Ethernet frame
+const UDP_PAYLOAD: usize = 1472;
+
+/// Chunks of received samples on their way to the flowgraph
+pub type Chunks = mpsc::Sender<Vec<Complex32>>;
+
+/// How samples are exchanged over the network
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Connect to a server, e.g., GNU Radio's TCP Server Sink or TCP Server Source
+    TcpClient,
+    /// Wait for clients, e.g., GNU Radio's TCP Sink or TCP Source in client mode
+    TcpServer,
+    /// Datagrams, e.g., from GNU Radio's UDP Sink or to its UDP Source
+    Udp,
+}
+
+impl NetworkMode {
+    /// Mode selected in a ComboBox of the GUI
+    fn from_index(index: i32) -> Self {
+        // Same order as in the GUI
+        match index {
+            1 => Self::TcpServer,
+            2 => Self::Udp,
+            _ => Self::TcpClient,
+        }
+    }
+}
+
+/// Protocol, address and sample format of a network stream
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkStream {
+    pub mode: NetworkMode,
+    /// Host and port to connect or send to for clients, to listen on for servers and UDP input
+    pub address: String,
+    pub format: IqFormat,
+}
+
+impl NetworkStream {
+    /// Input set up in the Source tab of the GUI
+    pub fn input_from_app(app: &MainWindow) -> Self {
+        Self::new(
+            app.get_network_mode(),
+            app.get_source_address().as_str(),
+            app.get_network_format(),
+        )
+    }
+
+    /// Output set up in the Source tab of the GUI, `None` if it is disabled
+    pub fn output_from_app(app: &MainWindow) -> Option<Self> {
+        app.get_output_enable().then(|| {
+            Self::new(
+                app.get_output_mode(),
+                app.get_output_address().as_str(),
+                app.get_output_format(),
+            )
+        })
+    }
+
+    fn new(mode: i32, address: &str, format: i32) -> Self {
+        Self {
+            mode: NetworkMode::from_index(mode),
+            address: address.trim().to_string(),
+            // Same order as in the GUI
+            format: match format {
+                1 => IqFormat::Ci16,
+                _ => IqFormat::Cf32,
+            },
+        }
+    }
+
+    /// Receive samples on a separate thread and show the connection in the GUI. The returned
+    /// block streams the samples until the thread stops.
+    pub fn receive(&self, window: Weak<MainWindow>) -> Result<(ReceiveThread, Block)> {
+        let stream = self.clone();
+        ReceiveThread::spawn("network input", move |stop, mut chunks| {
+            stream.receive_until(stop, &mut chunks, &window)
+        })
+    }
+
+    fn receive_until(
+        &self,
+        stop: &AtomicBool,
+        chunks: &mut Chunks,
+        window: &Weak<MainWindow>,
+    ) -> Result<()> {
+        let address = &self.address;
+        match self.mode {
+            NetworkMode::TcpClient => {
+                show_connection(window, ConnectionState::Connecting, address.clone());
+                let mut socket = connect(address)?;
+                socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                show_connection(window, ConnectionState::Connected, address.clone());
+                let closed = decode_into(self.format, |b| socket.read(b), chunks, stop)
+                    .map_err(|e| Error::Source(format!("connection to {address} lost: {e}")))?;
+                if closed {
+                    return Err(Error::Source(format!("{address} closed the connection")));
+                }
+            }
+            NetworkMode::TcpServer => {
+                let listener = TcpListener::bind(address)
+                    .map_err(|e| Error::Source(format!("cannot listen on {address}: {e}")))?;
+                listener.set_nonblocking(true)?;
+                let waiting = format!("waiting for a client on {address}");
+                show_connection(window, ConnectionState::Connecting, waiting.clone());
+                // Serve one client after the other
+                while !stop.load(Ordering::Relaxed) {
+                    let (mut socket, peer) = match listener.accept() {
+                        Ok(accepted) => accepted,
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(e) => return Err(Error::Source(format!("cannot accept client: {e}"))),
+                    };
+                    socket.set_nonblocking(false)?;
+                    socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                    info!("Receiving samples from {peer}");
+                    show_connection(window, ConnectionState::Connected, peer.to_string());
+                    match decode_into(self.format, |b| socket.read(b), chunks, stop) {
+                        Ok(true) => info!("{peer} closed the connection"),
+                        Ok(false) => break,
+                        Err(e) => info!("Connection to {peer} lost: {e}"),
+                    }
+                    show_connection(window, ConnectionState::Connecting, waiting.clone());
+                }
+            }
+            NetworkMode::Udp => {
+                let socket = UdpSocket::bind(address)
+                    .map_err(|e| Error::Source(format!("cannot listen on {address}: {e}")))?;
+                socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                show_connection(
+                    window,
+                    ConnectionState::Connecting,
+                    format!("waiting for datagrams on {address}"),
+                );
+                // Show the sender of the most recent datagram
+                let mut sender = None;
+                let read = |bytes: &mut [u8]| {
+                    let (n, from) = socket.recv_from(bytes)?;
+                    if sender != Some(from) {
+                        sender = Some(from);
+                        show_connection(window, ConnectionState::Connected, from.to_string());
+                    }
+                    // Empty datagrams carry no samples, but don't end the stream either
+                    if n == 0 {
+                        return Err(io::ErrorKind::Interrupted.into());
+                    }
+                    Ok(n)
+                };
+                decode_into(self.format, read, chunks, stop)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send samples on a separate thread. The returned block takes the samples to send, the
+    /// thread stops when the block is dropped.
+    pub fn send(&self) -> Result<(SendThread, Block)> {
+        let (payloads, sent) = sync_channel(CHUNK_BUFFER);
+        let error = Arc::new(Mutex::new(None));
+
+        let stream = self.clone();
+        let thread_error = error.clone();
+        thread::Builder::new()
+            .name("network output".to_string())
+            .spawn(move || {
+                if let Err(e) = stream.send_all(sent) {
+                    *thread_error.lock().unwrap() = Some(e.to_string());
+                }
+            })?;
+
+        Ok((
+            SendThread { error },
+            NetworkSink::new(self.format, payloads),
+        ))
+    }
+
+    fn send_all(&self, payloads: Receiver<Vec<u8>>) -> Result<()> {
+        let address = &self.address;
+        let lost = |e: io::Error| Error::Source(format!("cannot send to {address}: {e}"));
+        match self.mode {
+            NetworkMode::TcpClient => {
+                let mut socket = connect(address)?;
+                info!("Sending samples to {address}");
+                while let Ok(payload) = payloads.recv() {
+                    socket.write_all(&payload).map_err(lost)?;
+                }
+            }
+            NetworkMode::TcpServer => {
+                let listener = TcpListener::bind(address)
+                    .map_err(|e| Error::Source(format!("cannot listen on {address}: {e}")))?;
+                listener.set_nonblocking(true)?;
+                // All clients get the same samples, like from GNU Radio's TCP Server Sink
+                let mut clients: Vec<(TcpStream, SocketAddr)> = Vec::new();
+                loop {
+                    while let Ok((socket, peer)) = listener.accept() {
+                        socket.set_nonblocking(false)?;
+                        socket.set_nodelay(true)?;
+                        info!("Sending samples to {peer}");
+                        clients.push((socket, peer));
+                    }
+                    match payloads.recv_timeout(POLL_INTERVAL) {
+                        Ok(payload) => clients.retain_mut(|(socket, peer)| {
+                            let sent = socket.write_all(&payload);
+                            if let Err(e) = &sent {
+                                info!("Stopped sending samples to {peer}: {e}");
+                            }
+                            sent.is_ok()
+                        }),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            }
+            NetworkMode::Udp => {
+                let target = resolve(address)?;
+                let local = if target.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = UdpSocket::bind(local)?;
+                info!("Sending samples to {target}");
+                // Datagrams only contain whole samples, so the receiver can decode each on its own
+                let size = self.format.sample_size();
+                let max_payload = UDP_PAYLOAD / size * size;
+                while let Ok(payload) = payloads.recv() {
+                    for datagram in payload.chunks(max_payload) {
+                        socket.send_to(datagram, target).map_err(lost)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handle of a thread receiving samples, which is stopped when the handle is dropped
+pub struct ReceiveThread {
+    stop: Arc<AtomicBool>,
+    /// Why the thread stopped, if it failed
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl ReceiveThread {
+    /// Run `receive` on a thread called `name`. It must check the stop flag at least every
+    /// [`POLL_INTERVAL`] and pass the received samples on to the chunks, which are streamed by
+    /// the returned block.
+    pub fn spawn(
+        name: &str,
+        receive: impl FnOnce(&AtomicBool, Chunks) -> Result<()> + Send + 'static,
+    ) -> Result<(Self, Block)> {
+        let (chunks, received) = mpsc::channel(CHUNK_BUFFER);
+        let stop = Arc::new(AtomicBool::new(false));
+        let error = Arc::new(Mutex::new(None));
+
+        let thread_stop = stop.clone();
+        let thread_error = error.clone();
+        thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                if let Err(e) = receive(&thread_stop, chunks) {
+                    *thread_error.lock().unwrap() = Some(e.to_string());
+                }
+            })?;
+
+        Ok((Self { stop, error }, ChannelSource::new(received)))
+    }
+
+    /// Why receiving samples failed, e.g., because the connection was lost
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+impl Drop for ReceiveThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Handle of a thread sending samples
+pub struct SendThread {
+    /// Why the thread stopped, if it failed
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl SendThread {
+    /// Why sending samples failed, e.g., because the connection was lost
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+}
+
+/// Decode the samples of `format` returned by `receive` and pass them on in chunks, until `stop`
+/// is set or the chunks are not received anymore. `receive` returns 0 at the end of the stream and
+/// may fail with `WouldBlock`, `TimedOut` or `Interrupted` to check `stop` in the meantime.
+///
+/// Returns whether the stream ended, i.e., the peer closed the connection.
+pub fn decode_into(
+    format: IqFormat,
+    mut receive: impl FnMut(&mut [u8]) -> io::Result<usize>,
+    chunks: &mut Chunks,
+    stop: &AtomicBool,
+) -> io::Result<bool> {
+    let size = format.sample_size();
+    let mut bytes = vec![0; CHUNK_SIZE];
+    // An incomplete sample can be left over from the previous read
+    let mut pending = 0;
+
+    while !stop.load(Ordering::Relaxed) {
+        let read = match receive(&mut bytes[pending..]) {
+            Ok(0) => return Ok(true),
+            Ok(read) => read,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+
+        let available = pending + read;
+        let m = available / size;
+        let samples = bytes[..m * size]
+            .chunks_exact(size)
+            .map(|sample| format.decode(sample))
+            .collect();
+        bytes.copy_within(m * size..available, 0);
+        pending = available - m * size;
+
+        if let Err(e) = chunks.try_send(samples) {
+            if e.is_disconnected() {
+                break;
+            }
+            debug!("Flowgraph is behind, dropping received samples");
+        }
+    }
+    Ok(false)
+}
+
+/// First socket address `address` resolves to
+pub fn resolve(address: &str) -> Result<SocketAddr> {
+    address
+        .to_socket_addrs()
+        .map_err(|e| Error::Source(format!("cannot resolve {address}: {e}")))?
+        .next()
+        .ok_or_else(|| Error::Source(format!("cannot resolve {address}")))
+}
+
+/// Connect to the TCP server at `address`, waiting at most [`CONNECT_TIMEOUT`]
+pub fn connect(address: &str) -> Result<TcpStream> {
+    let failed = |e: io::Error| Error::Source(format!("cannot connect to {address}: {e}"));
+    let socket = TcpStream::connect_timeout(&resolve(address)?, CONNECT_TIMEOUT).map_err(failed)?;
+    socket.set_nodelay(true).map_err(failed)?;
+    Ok(socket)
+}
+
+/// Show the connection of a network source in the GUI, from any thread
+pub fn show_connection(window: &Weak<MainWindow>, state: ConnectionState, details: String) {
+    // Nothing to show once the event loop has stopped
+    let _ = window.upgrade_in_event_loop(move |app| {
+        app.set_connection_state(state);
+        app.set_connection_details(details.into());
+    });
+}
+
+/// Send samples from a Flowgraph to a thread, which sends them over the network.
+///
+/// # Inputs
+///
+/// `in`: Samples to send, encoded in the given format
+///
+/// Samples are dropped if the thread cannot keep up, e.g., because of a slow connection.
+pub struct NetworkSink {
+    format: IqFormat,
+    tx: SyncSender<Vec<u8>>,
+}
+
+impl NetworkSink {
+    fn new(format: IqFormat, tx: SyncSender<Vec<u8>>) -> Block {
+        Block::new(
+            BlockMetaBuilder::new("NetworkSink").build(),
+            StreamIoBuilder::new().add_input::<Complex32>("in").build(),
+            MessageIoBuilder::<Self>::new().build(),
+            Self { format, tx },
+        )
+    }
+}
+
+#[doc(hidden)]
+#[async_trait]
+impl Kernel for NetworkSink {
+    async fn work(
+        &mut self,
+        io: &mut WorkIo,
+        sio: &mut StreamIo,
+        _mio: &mut MessageIo<Self>,
+        _meta: &mut BlockMeta,
+    ) -> anyhow::Result<()> {
+        let i = sio.input(0).slice::<Complex32>();
+
+        if sio.input(0).finished() {
+            io.finished = true;
+        }
+
+        if !i.is_empty() {
+            let mut payload = Vec::with_capacity(i.len() * self.format.sample_size());
+            for sample in i.iter() {
+                self.format.encode(*sample, &mut payload);
+            }
+            let n = i.len();
+            sio.input(0).consume(n);
+            match self.tx.try_send(payload) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => debug!("Network is behind, dropping samples"),
+                // The thread failed, its error is reported by the flowgraph's owner
+                Err(TrySendError::Disconnected(_)) => io.finished = true,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Run [`decode_into`] on the results of `reads`, which must end the stream, and collect the
+    /// decoded samples
+    fn decode(format: IqFormat, reads: Vec<io::Result<Vec<u8>>>) -> Vec<Complex32> {
+        let mut reads = VecDeque::from(reads);
+        let receive = |buffer: &mut [u8]| -> io::Result<usize> {
+            let bytes = reads.pop_front().expect("stream not ended")?;
+            buffer[..bytes.len()].copy_from_slice(&bytes);
+            Ok(bytes.len())
+        };
+        let (mut chunks, mut received) = mpsc::channel(CHUNK_BUFFER);
+        let ended = decode_into(format, receive, &mut chunks, &AtomicBool::new(false)).unwrap();
+        assert!(ended);
+
+        let mut samples = Vec::new();
+        while let Ok(chunk) = received.try_recv() {
+            samples.extend(chunk);
+        }
+        samples
+    }
+
+    fn encode(format: IqFormat, samples: &[Complex32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for sample in samples {
+            format.encode(*sample, &mut bytes);
+        }
+        bytes
+    }
+
+    #[test]
+    fn decode_sample_split_across_reads() {
+        let samples = [Complex32::new(0.5, -0.25), Complex32::new(-1.0, 1.0)];
+        let bytes = encode(IqFormat::Cf32, &samples);
+        // The first sample ends in the middle of the second read
+        let reads = vec![
+            Ok(bytes[..3].to_vec()),
+            Ok(bytes[3..11].to_vec()),
+            Ok(bytes[11..].to_vec()),
+            Ok(Vec::new()),
+        ];
+        assert_eq!(decode(IqFormat::Cf32, reads), samples);
+    }
+
+    #[test]
+    fn drop_incomplete_sample_at_end_of_stream() {
+        let mut bytes = encode(IqFormat::Ci16, &[Complex32::new(0.5, -0.5)]);
+        bytes.extend_from_slice(&[1, 2]);
+        let samples = decode(IqFormat::Ci16, vec![Ok(bytes), Ok(Vec::new())]);
+        assert_eq!(samples, [Complex32::new(0.5, -0.5)]);
+        assert!(decode(IqFormat::Cu8, vec![Ok(Vec::new())]).is_empty());
+    }
+
+    #[test]
+    fn retry_after_timeouts() {
+        let bytes = encode(IqFormat::Cu8, &[Complex32::new(1.0, -1.0)]);
+        let reads = vec![
+            Err(io::ErrorKind::WouldBlock.into()),
+            Ok(bytes[..1].to_vec()),
+            Err(io::ErrorKind::TimedOut.into()),
+            Err(io::ErrorKind::Interrupted.into()),
+            Ok(bytes[1..].to_vec()),
+            Ok(Vec::new()),
+        ];
+        assert_eq!(decode(IqFormat::Cu8, reads), [Complex32::new(1.0, -1.0)]);
+    }
+
+    #[test]
+    fn fail_on_other_errors() {
+        let (mut chunks, _received) = mpsc::channel(CHUNK_BUFFER);
+        let receive =
+            |_: &mut [u8]| -> io::Result<usize> { Err(io::ErrorKind::ConnectionReset.into()) };
+        let result = decode_into(
+            IqFormat::Cf32,
+            receive,
+            &mut chunks,
+            &AtomicBool::new(false),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionReset);
+    }
+}
@@ -0,0 +1,240 @@
+//! Client for servers speaking the `rtl_tcp` protocol, e.g., `rtl_tcp` of librtlsdr or the
+//! `rtl_tcp_server` stand-in of this crate.
+//!
+//! After connecting, the server sends a 12 byte dongle header followed by an endless stream of
+//! interleaved 8 bit unsigned I and Q samples. The client controls the dongle with 5 byte commands:
+//! a command byte followed by a big-endian `u32` parameter.
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use futuresdr::{futures::channel::oneshot, runtime::Block};
+use log::{debug, info};
+
+use super::{
+    iq_file::IqFormat,
+    network::{connect, decode_into, ReceiveThread, CONNECT_TIMEOUT, POLL_INTERVAL},
+};
+use crate::{
+    error::{Error, Result},
+    MainWindow,
+};
+
+/// Command bytes of the protocol
+const SET_FREQUENCY: u8 = 0x01;
+const SET_SAMPLE_RATE: u8 = 0x02;
+const SET_GAIN_MODE: u8 = 0x03;
+const SET_GAIN: u8 = 0x04;
+
+/// Dongle settings, as set up in the Source tab of the GUI
+#[derive(Clone, Debug, PartialEq)]
+pub struct RtlTcpConfig {
+    /// Host and port of the server
+    pub address: String,
+    /// Center frequency in Hz
+    pub frequency: u32,
+    /// Samples per second
+    pub sample_rate: u32,
+    /// Gain in tenths of dB, automatic gain control if `None`
+    pub gain: Option<u32>,
+}
+
+impl RtlTcpConfig {
+    /// Settings of the Source tab of the GUI
+    pub fn from_app(app: &MainWindow) -> Self {
+        Self {
+            address: app.get_source_address().trim().to_string(),
+            frequency: app.get_rtl_frequency().max(0) as u32 * 1000,
+            sample_rate: app.get_source_sample_rate().max(1) as u32 * 1000,
+            gain: (!app.get_rtl_auto_gain()).then_some(app.get_rtl_gain().max(0) as u32),
+        }
+    }
+}
+
+/// Tuner of the dongle and the number of gain steps it supports, as sent by the server
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DongleInfo {
+    pub tuner: u32,
+    pub gain_count: u32,
+}
+
+impl DongleInfo {
+    /// Parse the header starting with the magic `RTL0`, `None` if it is not an rtl_tcp server
+    fn parse(header: &[u8; 12]) -> Option<Self> {
+        if &header[..4] != b"RTL0" {
+            return None;
+        }
+        Some(Self {
+            tuner: u32::from_be_bytes(header[4..8].try_into().ok()?),
+            gain_count: u32::from_be_bytes(header[8..12].try_into().ok()?),
+        })
+    }
+
+    /// Name of the tuner chip, following the numbering of librtlsdr
+    pub fn tuner_name(&self) -> &'static str {
+        match self.tuner {
+            1 => "E4000",
+            2 => "FC0012",
+            3 => "FC0013",
+            4 => "FC2580",
+            5 => "R820T",
+            6 => "R828D",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Connection to an rtl_tcp server to change the settings of the dongle
+pub struct RtlTcp {
+    /// Settings last sent to the server
+    config: RtlTcpConfig,
+    /// Clone of the socket for sending commands
+    commands: TcpStream,
+    pub info: DongleInfo,
+}
+
+impl RtlTcp {
+    /// Connect to the server of `config` and send all settings. Returns the client to change the
+    /// settings later, and the thread receiving the samples with the block streaming them. The
+    /// thread stops when its handle is dropped or the server closes the connection.
+    pub async fn connect(config: RtlTcpConfig) -> Result<(Self, ReceiveThread, Block)> {
+        let (connected, connection) = oneshot::channel();
+
+        // Connecting blocks, so it is done by the receiving thread to keep the GUI responsive
+        let address = config.address.clone();
+        let (receiving, block) = ReceiveThread::spawn("rtl_tcp", move |stop, mut chunks| {
+            let (mut stream, info) = match open(&address) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    let _ = connected.send(Err(e));
+                    return Ok(());
+                }
+            };
+            let commands = stream
+                .try_clone()
+                .map_err(|e| Error::Source(format!("cannot send rtl_tcp commands: {e}")));
+            if connected.send(commands.map(|c| (c, info))).is_err() {
+                // Cancelled while connecting
+                return Ok(());
+            }
+            let closed = decode_into(IqFormat::Cu8, |b| stream.read(b), &mut chunks, stop)
+                .map_err(|e| Error::Source(format!("rtl_tcp connection lost: {e}")))?;
+            if closed {
+                return Err(Error::Source(
+                    "rtl_tcp server closed the connection".to_string(),
+                ));
+            }
+            Ok(())
+        })?;
+
+        let (stream, info) = connection
+            .await
+            .map_err(|_| Error::Source("rtl_tcp connection thread stopped".to_string()))??;
+        info!(
+            "Connected to rtl_tcp server at {} with {} tuner and {} gain steps",
+            config.address,
+            info.tuner_name(),
+            info.gain_count
+        );
+
+        let mut client = Self {
+            config,
+            commands: stream,
+            info,
+        };
+        client.send_all()?;
+        Ok((client, receiving, block))
+    }
+
+    /// Send the settings of `config` that differ from the ones sent before. The sample rate is
+    /// only sent when connecting, because the flowgraph is set up for a fixed rate.
+    pub fn update(&mut self, config: &RtlTcpConfig) -> Result<()> {
+        if config.frequency != self.config.frequency {
+            self.config.frequency = config.frequency;
+            self.send(SET_FREQUENCY, config.frequency)?;
+        }
+        if config.gain != self.config.gain {
+            self.config.gain = config.gain;
+            self.send_gain()?;
+        }
+        Ok(())
+    }
+
+    fn send_all(&mut self) -> Result<()> {
+        self.send(SET_SAMPLE_RATE, self.config.sample_rate)?;
+        self.send(SET_FREQUENCY, self.config.frequency)?;
+        self.send_gain()
+    }
+
+    fn send_gain(&mut self) -> Result<()> {
+        match self.config.gain {
+            Some(gain) => {
+                self.send(SET_GAIN_MODE, 1)?;
+                self.send(SET_GAIN, gain)
+            }
+            None => self.send(SET_GAIN_MODE, 0),
+        }
+    }
+
+    fn send(&mut self, command: u8, param: u32) -> Result<()> {
+        debug!("Sending rtl_tcp command {command:#04x} with {param}");
+        let mut message = [command; 5];
+        message[1..].copy_from_slice(&param.to_be_bytes());
+        self.commands
+            .write_all(&message)
+            .map_err(|e| Error::Source(format!("cannot send rtl_tcp command: {e}")))
+    }
+}
+
+/// Connect to `address` and read the dongle header
+fn open(address: &str) -> Result<(TcpStream, DongleInfo)> {
+    let failed = |e: std::io::Error| Error::Source(format!("cannot connect to {address}: {e}"));
+    let mut stream = connect(address)?;
+
+    stream
+        .set_read_timeout(Some(CONNECT_TIMEOUT))
+        .map_err(failed)?;
+    let mut header = [0; 12];
+    stream.read_exact(&mut header).map_err(failed)?;
+    let info = DongleInfo::parse(&header)
+        .ok_or_else(|| Error::Source(format!("{address} is not an rtl_tcp server")))?;
+
+    // Wake up regularly to check whether to stop
+    stream
+        .set_read_timeout(Some(POLL_INTERVAL))
+        .map_err(failed)?;
+    Ok((stream, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_dongle_info() {
+        let info = DongleInfo::parse(b"RTL0\0\0\0\x05\0\0\0\x1d").unwrap();
+        assert_eq!(
+            info,
+            DongleInfo {
+                tuner: 5,
+                gain_count: 29
+            }
+        );
+        assert_eq!(info.tuner_name(), "R820T");
+
+        // The fields are big-endian
+        let info = DongleInfo::parse(b"RTL0\x01\0\0\0\0\0\x01\0").unwrap();
+        assert_eq!(info.tuner, 1 << 24);
+        assert_eq!(info.gain_count, 256);
+        assert_eq!(info.tuner_name(), "unknown");
+    }
+
+    #[test]
+    fn reject_other_servers() {
+        assert_eq!(DongleInfo::parse(b"HTTP/1.1 200"), None);
+        assert_eq!(DongleInfo::parse(b"rtl0\0\0\0\x05\0\0\0\x1d"), None);
+        assert_eq!(DongleInfo::parse(&[0; 12]), None);
+    }
+}
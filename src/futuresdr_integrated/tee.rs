@@ -0,0 +1,79 @@
+use futuresdr::{
+    anyhow::Result,
+    macros::async_trait,
+    runtime::{
+        Block, BlockMeta, BlockMetaBuilder, Kernel, MessageIo, MessageIoBuilder, StreamIo,
+        StreamIoBuilder, WorkIo,
+    },
+};
+
+/// Copy a stream of samples to several outputs.
+///
+/// Slab buffers only support a single reader, so every output with more than one consumer goes
+/// through a tee. This also works in the browser, where there are no circular buffers.
+///
+/// # Inputs
+/// `in`: Input
+///
+/// # Outputs
+/// `out0`, `out1`, ...: Copies of the input, one per consumer
+pub struct Tee<T> {
+    outputs: usize,
+    _type: std::marker::PhantomData<T>,
+}
+
+impl<T> Tee<T>
+where
+    T: Copy + Send + 'static,
+{
+    pub fn new(outputs: usize) -> Block {
+        let mut stream_io = StreamIoBuilder::new().add_input::<T>("in");
+        for n in 0..outputs {
+            stream_io = stream_io.add_output::<T>(&format!("out{n}"));
+        }
+        Block::new(
+            BlockMetaBuilder::new("Tee").build(),
+            stream_io.build(),
+            MessageIoBuilder::<Self>::new().build(),
+            Self {
+                outputs,
+                _type: std::marker::PhantomData,
+            },
+        )
+    }
+}
+
+#[doc(hidden)]
+#[async_trait]
+impl<T> Kernel for Tee<T>
+where
+    T: Copy + Send + 'static,
+{
+    async fn work(
+        &mut self,
+        io: &mut WorkIo,
+        sio: &mut StreamIo,
+        _mio: &mut MessageIo<Self>,
+        _meta: &mut BlockMeta,
+    ) -> Result<()> {
+        let i = sio.input(0).slice::<T>();
+
+        // Every output gets the same samples, so the slowest consumer sets the pace
+        let m = (0..self.outputs)
+            .map(|n| sio.output(n).slice::<T>().len())
+            .fold(i.len(), std::cmp::min);
+        if m > 0 {
+            for n in 0..self.outputs {
+                sio.output(n).slice::<T>()[..m].copy_from_slice(&i[..m]);
+                sio.output(n).produce(m);
+            }
+            sio.input(0).consume(m);
+        }
+
+        if sio.input(0).finished() && m == i.len() {
+            io.finished = true;
+        }
+
+        Ok(())
+    }
+}
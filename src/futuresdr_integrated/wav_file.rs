@@ -0,0 +1,344 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use futuresdr::{num_complex::Complex32, runtime::Block};
+
+use super::file_source::{cannot_read, SampleFileSource};
+use crate::{
+    error::{Error, Result},
+    MainWindow,
+};
+
+/// `WAVE_FORMAT_PCM`, `WAVE_FORMAT_IEEE_FLOAT` and `WAVE_FORMAT_EXTENSIBLE` format tags
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// How the samples of a WAV file are stored
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WavEncoding {
+    /// Integers with 8 (unsigned), 16, 24 or 32 bits
+    Pcm(u16),
+    /// Floats with 32 or 64 bits
+    Float(u16),
+}
+
+impl WavEncoding {
+    fn bytes(self) -> usize {
+        match self {
+            Self::Pcm(bits) | Self::Float(bits) => bits as usize / 8,
+        }
+    }
+
+    /// Convert one sample of one channel to `-1.0..1.0`
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match (self, bytes) {
+            (Self::Pcm(8), [b]) => (*b as f32 - 128.0) / 128.0,
+            (Self::Pcm(16), [b0, b1]) => i16::from_le_bytes([*b0, *b1]) as f32 / 32768.0,
+            (Self::Pcm(24), [b0, b1, b2]) => {
+                i32::from_le_bytes([0, *b0, *b1, *b2]) as f32 / 2_147_483_648.0
+            }
+            (Self::Pcm(32), [b0, b1, b2, b3]) => {
+                i32::from_le_bytes([*b0, *b1, *b2, *b3]) as f32 / 2_147_483_648.0
+            }
+            (Self::Float(32), [b0, b1, b2, b3]) => f32::from_le_bytes([*b0, *b1, *b2, *b3]),
+            (Self::Float(64), bytes) => {
+                f64::from_le_bytes(bytes.try_into().unwrap_or_default()) as f32
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// An audio recording to play back
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavFile {
+    pub path: PathBuf,
+    pub channels: u16,
+    /// Frames per second as stored in the file
+    pub sample_rate: u32,
+    pub encoding: WavEncoding,
+    /// Byte offset and length of the sample data, up to the end of the file if the length is
+    /// unknown, e.g., for recordings that were not finished properly
+    pub data_start: u64,
+    pub data_len: Option<u64>,
+    /// Use the left and right channel of stereo files as I and Q instead of mixing them
+    pub stereo_as_iq: bool,
+    /// Start over at the end of the file instead of stopping
+    pub repeat: bool,
+}
+
+impl WavFile {
+    /// File at `path` with the settings of the Source tab of the GUI
+    pub fn from_app(app: &MainWindow, path: &Path) -> Result<Self> {
+        Self::open(path, app.get_wav_stereo_iq(), app.get_source_loop())
+    }
+
+    /// Read the header of the WAV file at `path`
+    pub fn open(path: &Path, stereo_as_iq: bool, repeat: bool) -> Result<Self> {
+        let invalid = |what: &str| Error::Source(format!("{}: {what}", path.display()));
+        let mut reader = BufReader::new(File::open(path).map_err(|e| cannot_read(path, e))?);
+
+        let mut riff = [0; 12];
+        reader
+            .read_exact(&mut riff)
+            .map_err(|e| cannot_read(path, e))?;
+        if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+            return Err(invalid("not a WAV file"));
+        }
+
+        // Chunks can come in any order, but the format must be known before the data
+        let mut format = None;
+        loop {
+            let mut header = [0; 8];
+            reader
+                .read_exact(&mut header)
+                .map_err(|_| invalid("no data chunk"))?;
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            // Chunks are padded to an even number of bytes
+            let padded = size as i64 + (size & 1) as i64;
+
+            match &header[..4] {
+                b"fmt " => {
+                    let mut fmt = vec![0; padded as usize];
+                    reader
+                        .read_exact(&mut fmt)
+                        .map_err(|e| cannot_read(path, e))?;
+                    let parsed = parse_format(&fmt).ok_or_else(|| invalid("unsupported format"))?;
+                    format = Some(parsed);
+                }
+                b"data" => {
+                    let (channels, sample_rate, encoding) =
+                        format.ok_or_else(|| invalid("data before format"))?;
+                    let data_start = reader.stream_position().map_err(|e| cannot_read(path, e))?;
+                    // Streaming writers leave the size at 0 or the maximum
+                    let data_len = (size != 0 && size != u32::MAX).then_some(size as u64);
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                        channels,
+                        sample_rate,
+                        encoding,
+                        data_start,
+                        data_len,
+                        stereo_as_iq,
+                        repeat,
+                    });
+                }
+                _ => {
+                    reader
+                        .seek(SeekFrom::Current(padded))
+                        .map_err(|e| cannot_read(path, e))?;
+                }
+            }
+        }
+    }
+
+    /// Whether the samples are real, i.e., their spectrum is symmetric
+    pub fn is_real(&self) -> bool {
+        !(self.stereo_as_iq && self.channels >= 2)
+    }
+
+    /// Block playing back the samples. Mono and mixed stereo samples become the real part, I/Q
+    /// stereo samples take the left channel as I and the right one as Q. Further channels are
+    /// ignored.
+    pub fn source(&self) -> Result<Block> {
+        let encoding = self.encoding;
+        let bytes = encoding.bytes();
+        let frame_size = bytes * self.channels as usize;
+        let stereo = self.channels >= 2;
+        let stereo_as_iq = self.stereo_as_iq;
+
+        SampleFileSource::new(
+            &self.path,
+            self.data_start,
+            self.data_len,
+            frame_size,
+            move |frame| {
+                let left = encoding.decode(&frame[..bytes]);
+                if !stereo {
+                    return Complex32::new(left, 0.0);
+                }
+                let right = encoding.decode(&frame[bytes..2 * bytes]);
+                if stereo_as_iq {
+                    Complex32::new(left, right)
+                } else {
+                    Complex32::new((left + right) / 2.0, 0.0)
+                }
+            },
+            self.repeat,
+        )
+    }
+}
+
+/// Channels, sample rate and encoding from the content of a `fmt ` chunk, `None` if unsupported
+fn parse_format(fmt: &[u8]) -> Option<(u16, u32, WavEncoding)> {
+    let u16_at = |i: usize| Some(u16::from_le_bytes(fmt.get(i..i + 2)?.try_into().ok()?));
+    let mut tag = u16_at(0)?;
+    let channels = u16_at(2)?;
+    let sample_rate = u32::from_le_bytes(fmt.get(4..8)?.try_into().ok()?);
+    let bits = u16_at(14)?;
+    // The actual format is the first two bytes of the sub-format GUID
+    if tag == FORMAT_EXTENSIBLE {
+        tag = u16_at(24)?;
+    }
+
+    let encoding = match (tag, bits) {
+        (FORMAT_PCM, 8 | 16 | 24 | 32) => WavEncoding::Pcm(bits),
+        (FORMAT_FLOAT, 32 | 64) => WavEncoding::Float(bits),
+        _ => return None,
+    };
+    (channels > 0 && sample_rate > 0).then_some((channels, sample_rate, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Content of a 16 byte `fmt ` chunk
+    fn fmt(tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&sample_rate.to_le_bytes());
+        fmt.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        fmt
+    }
+
+    /// Content of a 40 byte `fmt ` chunk of `WAVE_FORMAT_EXTENSIBLE` with the sub-format `tag`
+    fn extensible_fmt(tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let mut fmt = fmt(FORMAT_EXTENSIBLE, channels, sample_rate, bits);
+        // Size of the extension, valid bits and channel mask
+        fmt.extend_from_slice(&22u16.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+        fmt.extend_from_slice(&3u32.to_le_bytes());
+        // The rest of the GUID is the same for all formats
+        fmt.extend_from_slice(&tag.to_le_bytes());
+        fmt.extend_from_slice(b"\x00\x00\x00\x00\x10\x00\x80\x00\x00\xaa\x00\x38\x9b\x71");
+        fmt
+    }
+
+    /// RIFF header followed by the `chunks`, which are padded to an even size
+    fn wav(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        for (id, content) in chunks {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            body.extend_from_slice(content);
+            if content.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    /// Write `content` to a temporary file and open it
+    fn open(name: &str, content: &[u8]) -> Result<WavFile> {
+        let path = std::env::temp_dir().join(format!("{}-{name}.wav", std::process::id()));
+        fs::write(&path, content).unwrap();
+        let wav = WavFile::open(&path, false, false);
+        fs::remove_file(&path).unwrap();
+        wav
+    }
+
+    fn assert_invalid(wav: Result<WavFile>, reason: &str) {
+        match wav {
+            Err(Error::Source(e)) => assert!(e.contains(reason), "{e}"),
+            wav => panic!("expected {reason}, got {wav:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_supported_formats() {
+        assert_eq!(
+            parse_format(&fmt(FORMAT_PCM, 2, 44100, 16)),
+            Some((2, 44100, WavEncoding::Pcm(16)))
+        );
+        assert_eq!(
+            parse_format(&fmt(FORMAT_PCM, 1, 8000, 8)),
+            Some((1, 8000, WavEncoding::Pcm(8)))
+        );
+        assert_eq!(
+            parse_format(&fmt(FORMAT_FLOAT, 1, 48000, 64)),
+            Some((1, 48000, WavEncoding::Float(64)))
+        );
+        assert_eq!(
+            parse_format(&extensible_fmt(FORMAT_PCM, 2, 96000, 24)),
+            Some((2, 96000, WavEncoding::Pcm(24)))
+        );
+        assert_eq!(
+            parse_format(&extensible_fmt(FORMAT_FLOAT, 4, 192000, 32)),
+            Some((4, 192000, WavEncoding::Float(32)))
+        );
+    }
+
+    #[test]
+    fn reject_unsupported_formats() {
+        // ADPCM
+        assert_eq!(parse_format(&fmt(2, 1, 8000, 4)), None);
+        assert_eq!(parse_format(&fmt(FORMAT_PCM, 1, 8000, 12)), None);
+        assert_eq!(parse_format(&fmt(FORMAT_FLOAT, 1, 8000, 16)), None);
+        assert_eq!(parse_format(&fmt(FORMAT_PCM, 0, 8000, 16)), None);
+        assert_eq!(parse_format(&fmt(FORMAT_PCM, 1, 0, 16)), None);
+        assert_eq!(parse_format(&extensible_fmt(2, 1, 8000, 16)), None);
+    }
+
+    #[test]
+    fn reject_truncated_formats() {
+        let pcm = fmt(FORMAT_PCM, 1, 8000, 16);
+        assert_eq!(parse_format(&pcm[..15]), None);
+        assert_eq!(parse_format(&pcm[..2]), None);
+        assert_eq!(parse_format(&[]), None);
+        // The sub-format is missing
+        let extensible = extensible_fmt(FORMAT_PCM, 1, 8000, 16);
+        assert_eq!(parse_format(&extensible[..25]), None);
+    }
+
+    #[test]
+    fn open_file_with_padded_chunks() {
+        let fmt = fmt(FORMAT_PCM, 2, 44100, 16);
+        let wav = open(
+            "padded",
+            &wav(&[(b"LIST", b"odd"), (b"fmt ", &fmt), (b"data", &[0; 8])]),
+        )
+        .unwrap();
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.sample_rate, 44100);
+        assert_eq!(wav.encoding, WavEncoding::Pcm(16));
+        assert_eq!(wav.data_start, 12 + 12 + 24 + 8);
+        assert_eq!(wav.data_len, Some(8));
+    }
+
+    #[test]
+    fn reject_malformed_files() {
+        let fmt = fmt(FORMAT_PCM, 1, 8000, 16);
+        let file = wav(&[(b"fmt ", &fmt), (b"data", &[0; 4])]);
+
+        assert_invalid(open("truncated-riff", &file[..6]), "cannot read");
+        assert_invalid(open("riff", b"RIFF\0\0\0\0AVI LIST"), "not a WAV file");
+        // The header ends in the middle of the format
+        assert_invalid(open("truncated-fmt", &file[..30]), "cannot read");
+        assert_invalid(open("no-data", &file[..36]), "no data chunk");
+        assert_invalid(
+            open("data-first", &wav(&[(b"data", &[0; 4]), (b"fmt ", &fmt)])),
+            "data before format",
+        );
+        assert_invalid(
+            open(
+                "unsupported",
+                &wav(&[(b"fmt ", &fmt[..12]), (b"data", &[])]),
+            ),
+            "unsupported format",
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Keep the most recently plotted spectra around, e.g., to export them later on.
+//!
+//! Frames are recorded on the thread that renders the plot, i.e., the GUI event loop.
+
+use std::{cell::RefCell, collections::VecDeque};
+
+/// Maximum number of frames kept in the history
+pub const HISTORY_LENGTH: usize = 1000;
+
+thread_local! {
+    static HISTORY: RefCell<VecDeque<Vec<f32>>> = RefCell::new(VecDeque::with_capacity(HISTORY_LENGTH));
+}
+
+/// Add `frame` to the history, dropping the oldest frame if it is full
+pub fn record(frame: &[f32]) {
+    HISTORY.with_borrow_mut(|history| {
+        if history.len() == HISTORY_LENGTH {
+            history.pop_front();
+        }
+        history.push_back(frame.to_vec());
+    });
+}
+
+/// The `n` most recent frames, oldest first
+pub fn latest(n: usize) -> Vec<Vec<f32>> {
+    HISTORY.with_borrow(|history| {
+        history
+            .iter()
+            .skip(history.len().saturating_sub(n))
+            .cloned()
+            .collect()
+    })
+}
@@ -3,9 +3,32 @@ pub const FFT_SIZE: usize = 512;
 /// Rate at which the throttle block should output samples. Usually a multiple of FFT_SIZE.
 pub const PLOT_RATE: usize = FFT_SIZE * 4;
 
+/// Crate error type, shown in the GUI instead of panicking
+pub mod error;
+
 /// Rendering with plotters
 pub mod rendering;
 
+/// Export spectrum data and save exported files on desktop or download them in the browser
+pub mod export;
+
+/// Most recently plotted spectra
+pub mod history;
+
+/// Start and stop the sample source
+pub mod supervisor;
+
+/// Settings persisted between sessions
+pub mod settings;
+
+/// Reference traces on the file system
+#[cfg(not(target_arch = "wasm32"))]
+pub mod reference;
+
+/// HTTP/JSON API to control the running app
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+
 slint::include_modules!();
 
 // If any combination of incompatible features is selected, compilation should fail
@@ -23,7 +46,7 @@ pub use futuresdr_integrated::wait_for_samples;
 
 #[cfg(feature = "replay_vec")]
 mod replay_vec;
-#[cfg(feature = "replay_vec")]
+#[cfg(any(feature = "replay_vec", feature = "render_cli"))]
 pub mod vector;
 #[cfg(feature = "replay_vec")]
 pub use replay_vec::wait_for_samples;
@@ -0,0 +1,32 @@
+//! Save and load reference traces as CSV files with a `bin,level` line per value.
+
+use std::{fs, io, path::Path};
+
+/// Write `trace` to the CSV file at `path`
+pub fn save(path: impl AsRef<Path>, trace: &[f32]) -> io::Result<()> {
+    let mut csv = String::from("bin,level\n");
+    for (bin, level) in trace.iter().enumerate() {
+        csv.push_str(&format!("{bin},{level}\n"));
+    }
+    fs::write(path, csv)
+}
+
+/// Read a trace from the CSV file at `path` that was written with [`save`]
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<f32>> {
+    fs::read_to_string(path)?
+        .lines()
+        .skip(1) // Header
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.split(',')
+                .nth(1)
+                .and_then(|level| level.trim().parse::<f32>().ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid reference trace line: {line}"),
+                    )
+                })
+        })
+        .collect()
+}
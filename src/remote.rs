@@ -0,0 +1,693 @@
+//! Remote control of the running app over a local HTTP/JSON API, e.g., for scripted demos and
+//! automated tests.
+//!
+//! Like FutureSDR's control port, the API only changes what the GUI controls as well: setting the
+//! noise goes through the same message call to the flowgraph as the noise SpinBox. Each request
+//! is answered on the GUI event loop, so it sees and changes the same state as the user.
+//!
+//! | Method      | Path            | Body and response                                            |
+//! |-------------|-----------------|--------------------------------------------------------------|
+//! | GET         | `/api/state`    | `{"noise", "y_min", "y_max", "auto_range", "plot_enable"}`   |
+//! | PUT or POST | `/api/state`    | Any of the fields above to change, responds with the state   |
+//! | GET         | `/api/frame`    | `{"sample_rate", "bins": [...]}` of the latest spectrum      |
+//! | POST        | `/api/snapshot` | Optional `{"path", "width", "height"}`, defaults as in GUI   |
+//!
+//! The API is off unless enabled with [`ADDRESS_VARIABLE`]. Any local process may use it, but web
+//! pages may not: requests with an `Origin` header are refused, and PUT and POST requests must be
+//! `application/json`, which browsers do not send across origins without asking first. The `Host`
+//! must be `localhost`, `127.0.0.1` or `[::1]` with the port of the API, so pages cannot get
+//! around this by resolving their own domain to a local address (DNS rebinding). Snapshots are
+//! only written to [`SNAPSHOT_DIRECTORY`].
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    ops::RangeInclusive,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use slint::{ComponentHandle, Model, Weak};
+
+use crate::{
+    rendering::snapshot::{save_snapshot, SnapshotFormat},
+    MainWindow, PlotMeta,
+};
+
+/// Address the API listens on if enabled with `on`, next to FutureSDR's control port
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:1338";
+/// Environment variable to enable the API, either `on` or the address to listen on
+pub const ADDRESS_VARIABLE: &str = "PLOTTER_API";
+/// Directory in the working directory the snapshots requested over the API are saved to
+pub const SNAPSHOT_DIRECTORY: &str = "snapshots";
+/// Time to wait for a request to arrive and to be handled by the event loop
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest request line and headers accepted
+const MAX_HEAD: usize = 8 * 1024;
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 32;
+/// Largest request body accepted
+const MAX_BODY: usize = 64 * 1024;
+/// Values of the noise SpinBox in the GUI, i.e., ten times the noise level
+const NOISE_RANGE: RangeInclusive<i32> = 1..=10000;
+/// Values of the y-axis SpinBoxes in the GUI
+const Y_RANGE: RangeInclusive<i32> = 0..=10000;
+/// Largest width and height of a snapshot in pixels. Snapshots are rendered on the event loop, so
+/// this limits how long a request can block the GUI and how much memory it takes.
+const MAX_SNAPSHOT_SIZE: i32 = 4096;
+
+/// Serve the API on a separate thread if enabled with [`ADDRESS_VARIABLE`]
+pub fn start(window: Weak<MainWindow>) {
+    let address = match std::env::var(ADDRESS_VARIABLE) {
+        Ok(address) if address == "on" => DEFAULT_ADDRESS.to_string(),
+        Ok(address) if !address.is_empty() && address != "off" => address,
+        _ => return,
+    };
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Remote control API not available, cannot listen on {address}: {e}");
+            return;
+        }
+    };
+    let port = match listener.local_addr() {
+        Ok(local) => local.port(),
+        Err(e) => {
+            warn!("Remote control API not available: {e}");
+            return;
+        }
+    };
+    info!("Remote control API listening on http://{address}/api/");
+
+    let spawned = thread::Builder::new()
+        .name("remote api".to_string())
+        .spawn(move || {
+            // Requests are short, so they are served one after the other
+            for stream in listener.incoming().flatten() {
+                if let Err(e) = serve(stream, &window, port) {
+                    debug!("Remote control request failed: {e}");
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Remote control API not available: {e}");
+    }
+}
+
+/// Answer the request on `stream` to the API listening on `port` and close the connection
+fn serve(mut stream: TcpStream, window: &Weak<MainWindow>, port: u16) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let response = match read_request(&mut stream) {
+        Ok(request) => match check(&request, port) {
+            Ok(()) => {
+                // Handled on the event loop, which owns the GUI state
+                let (respond, response) = mpsc::channel();
+                window
+                    .upgrade_in_event_loop(move |app| {
+                        let _ = respond.send(handle(&app, &request));
+                    })
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                response
+                    .recv_timeout(TIMEOUT)
+                    .unwrap_or_else(|_| Response::error(503, "event loop did not respond"))
+            }
+            Err(response) => response,
+        },
+        Err(e) => Response::error(400, &e.to_string()),
+    };
+    response.write_to(&mut stream)
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+    /// Whether the request was sent by a web page
+    origin: bool,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Read an HTTP/1.x request, only `Content-Length` is supported for bodies
+fn read_request(mut stream: impl Read) -> io::Result<Request> {
+    let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+
+    // Read until the request line and the headers are complete, they may arrive in pieces
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 1024];
+    let (head_length, mut request, content_length) = loop {
+        let read = stream.read(&mut chunk)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let head_length = match parsed.parse(&buffer) {
+            Ok(httparse::Status::Complete(length)) => length,
+            Ok(httparse::Status::Partial) if buffer.len() > MAX_HEAD => {
+                return Err(invalid("request header too large"))
+            }
+            Ok(httparse::Status::Partial) => continue,
+            Err(e) => return Err(invalid(&e.to_string())),
+        };
+
+        let header = |name: &str| {
+            parsed
+                .headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .map(|header| String::from_utf8_lossy(header.value).into_owned())
+        };
+        let content_length = match header("content-length") {
+            Some(length) => length
+                .trim()
+                .parse()
+                .map_err(|_| invalid("invalid Content-Length"))?,
+            None => 0,
+        };
+        // Ignore query parameters and a trailing slash
+        let target = parsed.path.unwrap_or_default();
+        let path = target.split('?').next().unwrap_or_default();
+        let request = Request {
+            method: parsed.method.unwrap_or_default().to_string(),
+            path: path.strip_suffix('/').unwrap_or(path).to_string(),
+            host: header("host"),
+            origin: header("origin").is_some(),
+            content_type: header("content-type"),
+            body: Vec::new(),
+        };
+        break (head_length, request, content_length);
+    };
+    if content_length > MAX_BODY {
+        return Err(invalid("request body too large"));
+    }
+
+    // Part of the body may have arrived with the headers
+    let mut body = buffer.split_off(head_length);
+    let received = body.len().min(content_length);
+    body.resize(content_length, 0);
+    stream.read_exact(&mut body[received..])?;
+    request.body = body;
+    Ok(request)
+}
+
+/// Refuse requests of web pages, which could otherwise use the API through the browser of the
+/// user. Browsers send an `Origin` header with cross-origin requests that change something, and
+/// they only send JSON to another origin after a preflight request, which is never answered.
+/// A page whose domain resolves to a local address is of the same origin, but the browser still
+/// sends that domain as `Host`.
+fn check(request: &Request, port: u16) -> Result<(), Response> {
+    if !request
+        .host
+        .as_deref()
+        .is_some_and(|host| is_local_host(host, port))
+    {
+        return Err(Response::error(
+            403,
+            &format!("Host must be localhost:{port}, 127.0.0.1:{port} or [::1]:{port}"),
+        ));
+    }
+    if request.origin {
+        return Err(Response::error(
+            403,
+            "requests from web pages are not allowed",
+        ));
+    }
+    let json = request.content_type.as_deref().is_some_and(|content_type| {
+        let media_type = content_type.split(';').next().unwrap_or_default();
+        media_type.trim().eq_ignore_ascii_case("application/json")
+    });
+    if matches!(request.method.as_str(), "PUT" | "POST") && !json {
+        return Err(Response::error(
+            415,
+            "Content-Type must be application/json",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `host` is a loopback name or address with `port`, which defaults to 80 like in URLs
+fn is_local_host(host: &str, port: u16) -> bool {
+    let (name, host_port) = match host.rsplit_once(':') {
+        // The colons of an IPv6 address are within the brackets
+        Some((name, host_port)) if !host.ends_with(']') => (name, host_port.parse().ok()),
+        _ => (host, Some(80)),
+    };
+    let name = name.to_ascii_lowercase();
+    matches!(name.as_str(), "localhost" | "127.0.0.1" | "[::1]") && host_port == Some(port)
+}
+
+struct Response {
+    status: u16,
+    json: Value,
+}
+
+impl Response {
+    fn ok(json: Value) -> Self {
+        Self { status: 200, json }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            json: json!({ "error": message }),
+        }
+    }
+
+    fn write_to(&self, mut stream: impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            415 => "Unsupported Media Type",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        };
+        let body = self.json.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            self.status,
+            body.len(),
+        )?;
+        stream.flush()
+    }
+}
+
+/// Answer `request` on the event loop
+fn handle(app: &MainWindow, request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/api/state") => Response::ok(state(app)),
+        ("PUT" | "POST", "/api/state") => {
+            match StateChange::parse(&request.body).and_then(|change| change.apply(app)) {
+                Ok(()) => Response::ok(state(app)),
+                Err(e) => Response::error(400, &e),
+            }
+        }
+        ("GET", "/api/frame") => Response::ok(frame(app)),
+        ("POST", "/api/snapshot") => snapshot(app, &request.body),
+        (_, "/api/state" | "/api/frame" | "/api/snapshot") => {
+            Response::error(405, "method not allowed")
+        }
+        _ => Response::error(404, "not found"),
+    }
+}
+
+/// Controls of the GUI, the noise level as used by the flowgraph instead of the SpinBox value
+fn state(app: &MainWindow) -> Value {
+    json!({
+        "noise": app.get_noise() as f64 / 10.0,
+        "y_min": app.get_y_axis_min(),
+        "y_max": app.get_y_axis_max(),
+        "auto_range": app.get_y_auto_update(),
+        "plot_enable": app.get_plot_enable(),
+    })
+}
+
+/// Controls to change, all or none of them
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateChange {
+    /// Noise level as used by the flowgraph, a tenth of the SpinBox value
+    noise: Option<f64>,
+    y_min: Option<i32>,
+    y_max: Option<i32>,
+    auto_range: Option<bool>,
+    plot_enable: Option<bool>,
+}
+
+impl StateChange {
+    /// Controls given in the JSON object `body`, which must be within the ranges the GUI allows
+    fn parse(body: &[u8]) -> Result<Self, String> {
+        let change: Self = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+        let (min, max) = NOISE_RANGE.into_inner();
+        if change
+            .noise
+            .is_some_and(|noise| !(min as f64..=max as f64).contains(&(noise * 10.0).round()))
+        {
+            return Err(format!(
+                "noise must be between {} and {}",
+                min as f64 / 10.0,
+                max as f64 / 10.0
+            ));
+        }
+        for (name, value) in [("y_min", change.y_min), ("y_max", change.y_max)] {
+            if value.is_some_and(|value| !Y_RANGE.contains(&value)) {
+                return Err(format!(
+                    "{name} must be between {} and {}",
+                    Y_RANGE.start(),
+                    Y_RANGE.end()
+                ));
+            }
+        }
+        if let (Some(y_min), Some(y_max)) = (change.y_min, change.y_max) {
+            if y_min >= y_max {
+                return Err("y_min must be less than y_max".to_string());
+            }
+        }
+        Ok(change)
+    }
+
+    /// Change the controls, the y-axis limits must be in order with the current ones as well
+    fn apply(self, app: &MainWindow) -> Result<(), String> {
+        if self.y_min.unwrap_or(app.get_y_axis_min()) >= self.y_max.unwrap_or(app.get_y_axis_max())
+        {
+            return Err("y_min must be less than y_max".to_string());
+        }
+
+        // The flowgraph picks up the new noise level like edits in the GUI
+        if let Some(noise) = self.noise {
+            app.set_noise((noise * 10.0).round() as i32);
+        }
+        if let Some(y_min) = self.y_min {
+            app.set_y_axis_min(y_min);
+        }
+        if let Some(y_max) = self.y_max {
+            app.set_y_axis_max(y_max);
+        }
+        if let Some(auto_range) = self.auto_range {
+            app.set_y_auto_update(auto_range);
+        }
+        if let Some(plot_enable) = self.plot_enable {
+            if plot_enable != app.get_plot_enable() {
+                app.set_plot_enable(plot_enable);
+                app.invoke_plot_enable_toggled();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Most recently plotted spectrum with the frequency span of its bins. JSON has no NaN or
+/// infinity, so bins with zero magnitude in dB become `null`.
+fn frame(app: &MainWindow) -> Value {
+    let meta = app.global::<PlotMeta>();
+    let bins: Vec<f32> = meta.get_last_frame().iter().collect();
+    json!({
+        "sample_rate": meta.get_sample_rate(),
+        "bins": bins,
+    })
+}
+
+/// Save a snapshot like the button in the GUI, with the GUI settings unless given in `body`
+fn snapshot(app: &MainWindow, body: &[u8]) -> Response {
+    let request = match SnapshotRequest::parse(
+        body,
+        &app.get_snapshot_path(),
+        app.get_snapshot_width(),
+        app.get_snapshot_height(),
+    ) {
+        Ok(request) => request,
+        Err(e) => return Response::error(400, &e),
+    };
+    let SnapshotRequest {
+        file_name,
+        width,
+        height,
+    } = request;
+
+    let path = Path::new(SNAPSHOT_DIRECTORY).join(file_name);
+    let path = path.to_string_lossy();
+    let saved = fs::create_dir_all(SNAPSHOT_DIRECTORY)
+        .map_err(Into::into)
+        .and_then(|()| save_snapshot(app, &path, width as u32, height as u32));
+    match saved {
+        Ok(true) => Response::ok(json!({ "path": path, "width": width, "height": height })),
+        Ok(false) => Response::error(400, "nothing has been plotted yet"),
+        Err(e) => Response::error(500, &e.to_string()),
+    }
+}
+
+/// File name and size of a snapshot
+#[derive(Debug, PartialEq)]
+struct SnapshotRequest {
+    /// Name of the file in [`SNAPSHOT_DIRECTORY`]
+    file_name: String,
+    width: i32,
+    height: i32,
+}
+
+impl SnapshotRequest {
+    /// Snapshot given in the JSON object `body`, which may be empty. Defaults to the file name of
+    /// `gui_path` and the size set in the GUI.
+    fn parse(body: &[u8], gui_path: &str, width: i32, height: i32) -> Result<Self, String> {
+        #[derive(Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            path: Option<String>,
+            width: Option<i32>,
+            height: Option<i32>,
+        }
+        let fields: Fields = if body.iter().all(u8::is_ascii_whitespace) {
+            Fields::default()
+        } else {
+            serde_json::from_slice(body).map_err(|e| e.to_string())?
+        };
+
+        let gui_file_name = Path::new(gui_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let request = Self {
+            file_name: fields.path.unwrap_or_else(|| gui_file_name.to_string()),
+            width: fields.width.unwrap_or(width),
+            height: fields.height.unwrap_or(height),
+        };
+
+        // Only plain file names, so scripts cannot write anywhere else
+        if request.file_name.contains(['/', '\\', ':'])
+            || SnapshotFormat::from_file_name(&request.file_name).is_none()
+        {
+            return Err(format!(
+                "path must be the name of a .png or .svg file in {SNAPSHOT_DIRECTORY}"
+            ));
+        }
+        if !(1..=MAX_SNAPSHOT_SIZE).contains(&request.width)
+            || !(1..=MAX_SNAPSHOT_SIZE).contains(&request.height)
+        {
+            return Err(format!(
+                "width and height must be between 1 and {MAX_SNAPSHOT_SIZE}"
+            ));
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Port the API listens on in the tests
+    const PORT: u16 = 1338;
+
+    #[test]
+    fn read_put_request() {
+        let text = "PUT /api/state/?pretty HTTP/1.1\r\nHost: localhost:1338\r\n\
+                    content-type: application/json\r\nContent-Length: 13\r\n\r\n{\"noise\":1.5}";
+        let request = read_request(text.as_bytes()).unwrap();
+        assert_eq!(request.method, "PUT");
+        assert_eq!(request.path, "/api/state");
+        assert_eq!(request.content_type.as_deref(), Some("application/json"));
+        assert!(!request.origin);
+        assert_eq!(request.body, b"{\"noise\":1.5}");
+        assert!(check(&request, PORT).is_ok());
+    }
+
+    /// The head and the body may arrive in several pieces
+    #[test]
+    fn read_request_in_pieces() {
+        let text = "POST /api/snapshot HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}";
+        let (start, rest) = text.as_bytes().split_at(7);
+        let request = read_request(start.chain(rest)).unwrap();
+        assert_eq!(request.path, "/api/snapshot");
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[test]
+    fn reject_malformed_requests() {
+        let requests: [&[u8]; 5] = [
+            b"",
+            b"GET\r\n\r\n",
+            b"GET /api/state HTTP/1.1\r\n",
+            b"POST /api/state HTTP/1.1\r\nContent-Length: many\r\n\r\n",
+            // Shorter than announced
+            b"POST /api/state HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}",
+        ];
+        for request in requests {
+            assert!(read_request(request).is_err(), "{request:?}");
+        }
+    }
+
+    #[test]
+    fn reject_large_requests() {
+        let text = format!(
+            "POST /api/state HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            MAX_BODY + 1,
+            " ".repeat(MAX_BODY + 1)
+        );
+        let error = read_request(text.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "request body too large");
+
+        let text = format!("GET /api/state HTTP/1.1\r\nX: {}", " ".repeat(MAX_HEAD));
+        let error = read_request(text.as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "request header too large");
+    }
+
+    #[test]
+    fn reject_requests_of_web_pages() {
+        let request = |head: &str| {
+            read_request(format!("{head}\r\nHost: 127.0.0.1:1338\r\n\r\n").as_bytes()).unwrap()
+        };
+        let status = |request: &Request| check(request, PORT).err().map(|response| response.status);
+
+        let origin = request("GET /api/state HTTP/1.1\r\nOrigin: https://example.com");
+        assert_eq!(status(&origin), Some(403));
+        let form = request("POST /api/state HTTP/1.1\r\nContent-Type: text/plain");
+        assert_eq!(status(&form), Some(415));
+        let untyped = request("POST /api/snapshot HTTP/1.1");
+        assert_eq!(status(&untyped), Some(415));
+
+        let json =
+            request("POST /api/snapshot HTTP/1.1\r\nContent-Type: Application/JSON; charset=utf-8");
+        assert_eq!(status(&json), None);
+        assert_eq!(status(&request("GET /api/frame HTTP/1.1")), None);
+    }
+
+    #[test]
+    fn reject_other_hosts() {
+        let status = |host: Option<&str>| {
+            let host = host.map_or(String::new(), |host| format!("Host: {host}\r\n"));
+            let text = format!("GET /api/state HTTP/1.1\r\n{host}\r\n");
+            let request = read_request(text.as_bytes()).unwrap();
+            check(&request, PORT).err().map(|response| response.status)
+        };
+
+        for host in [
+            "localhost:1338",
+            "LocalHost:1338",
+            "127.0.0.1:1338",
+            "[::1]:1338",
+        ] {
+            assert_eq!(status(Some(host)), None, "{host}");
+        }
+        let rejected = [
+            None,
+            Some(""),
+            // DNS rebinding, the domain resolves to 127.0.0.1
+            Some("attacker.example:1338"),
+            Some("localhost.attacker.example:1338"),
+            Some("localhost:8080"),
+            Some("localhost"),
+            Some("[::1]"),
+            Some("127.0.0.2:1338"),
+            Some("0.0.0.0:1338"),
+        ];
+        for host in rejected {
+            assert_eq!(status(host), Some(403), "{host:?}");
+        }
+        assert!(is_local_host("localhost", 80));
+        assert!(is_local_host("[::1]", 80));
+    }
+
+    #[test]
+    fn write_json_response() {
+        let mut bytes = Vec::new();
+        Response::error(404, "not \"found\"\n")
+            .write_to(&mut bytes)
+            .unwrap();
+        let text = String::from_utf8(bytes).unwrap();
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(text.ends_with("\r\n\r\n{\"error\":\"not \\\"found\\\"\\n\"}"));
+    }
+
+    #[test]
+    fn parse_state_change() {
+        let change =
+            StateChange::parse(br#"{"noise": 2.5, "y_min": 40, "plot_enable": false}"#).unwrap();
+        assert_eq!(
+            change,
+            StateChange {
+                noise: Some(2.5),
+                y_min: Some(40),
+                plot_enable: Some(false),
+                ..StateChange::default()
+            }
+        );
+        assert_eq!(StateChange::parse(b"{}"), Ok(StateChange::default()));
+
+        // The limits of the SpinBoxes
+        assert!(StateChange::parse(br#"{"noise": 0.1, "y_min": 0, "y_max": 10000}"#).is_ok());
+        assert!(StateChange::parse(br#"{"noise": 1000}"#).is_ok());
+
+        let rejected: [&[u8]; 14] = [
+            br#"{"noise": 1, "colour": "red"}"#,
+            br#"{"auto_range": 1}"#,
+            br#"{"y_min": 40.5}"#,
+            br#"{"noise": -1}"#,
+            br#"{"noise": 0.04}"#,
+            br#"{"noise": 1000.1}"#,
+            // Would saturate when converted to the SpinBox value
+            br#"{"noise": 1e300}"#,
+            br#"{"y_min": -1}"#,
+            br#"{"y_max": 10001}"#,
+            br#"{"y_min": 20, "y_max": 20}"#,
+            br#"{"y_min": 30, "y_max": 20}"#,
+            br#"{"noise": 1,}"#,
+            b"[1]",
+            b"",
+        ];
+        for body in rejected {
+            assert!(StateChange::parse(body).is_err(), "{body:?}");
+        }
+    }
+
+    #[test]
+    fn parse_snapshot_request() {
+        let parse =
+            |body: &str| SnapshotRequest::parse(body.as_bytes(), "/home/user/plot.svg", 800, 600);
+        assert_eq!(
+            parse(" "),
+            Ok(SnapshotRequest {
+                file_name: "plot.svg".to_string(),
+                width: 800,
+                height: 600,
+            })
+        );
+        assert_eq!(
+            parse(r#"{"path": "remote.png", "width": 4096, "height": 1}"#),
+            Ok(SnapshotRequest {
+                file_name: "remote.png".to_string(),
+                width: 4096,
+                height: 1,
+            })
+        );
+
+        let rejected = [
+            r#"{"path": "../remote.png"}"#,
+            r#"{"path": "/tmp/remote.png"}"#,
+            r#"{"path": "dir\\remote.png"}"#,
+            r#"{"path": "C:remote.png"}"#,
+            r#"{"path": "remote.txt"}"#,
+            r#"{"width": 4097}"#,
+            r#"{"height": 0}"#,
+            r#"{"width": 1e12}"#,
+            r#"{"format": "png"}"#,
+            "{",
+        ];
+        for body in rejected {
+            assert!(parse(body).is_err(), "{body}");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Smoothed automatic y-axis ranging.
+//!
+//! The limits follow percentiles of each frame instead of its exact minimum and maximum, so single
+//! outlier bins are ignored. Some headroom is added around the data. The range expands instantly
+//! when the data leaves it, but only shrinks gradually and once the data stays well inside of it,
+//! so the axis does not jump around with every frame.
+
+use std::cell::RefCell;
+
+use crate::MainWindow;
+
+/// User-adjustable behaviour of the auto-ranging
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoRangeSettings {
+    /// Space added above and below the data as a fraction of the data span
+    pub headroom: f32,
+    /// Time constant in seconds with which the range shrinks towards the data
+    pub decay_time: f32,
+    /// Percentile of the bins used as the upper limit, e.g. `0.99`. The lower limit uses the
+    /// mirrored percentile. `1.0` uses the exact minimum and maximum.
+    pub percentile: f32,
+}
+
+impl Default for AutoRangeSettings {
+    /// Same as the defaults in the GUI
+    fn default() -> Self {
+        Self {
+            headroom: 0.1,
+            decay_time: 2.0,
+            percentile: 0.99,
+        }
+    }
+}
+
+impl AutoRangeSettings {
+    /// Read the settings from the GUI, which shows them as percentages and milliseconds
+    pub fn from_app(app: &MainWindow) -> Self {
+        Self {
+            headroom: app.get_auto_range_headroom() as f32 / 100.0,
+            decay_time: app.get_auto_range_decay() as f32 / 1000.0,
+            percentile: app.get_auto_range_percentile() as f32 / 100.0,
+        }
+    }
+
+    /// Target limits for `data`: the percentiles plus headroom, rounded outwards to whole
+    /// numbers. `None` if there are no finite values.
+    pub fn limits(&self, data: &[f32]) -> Option<(f32, f32)> {
+        let mut values: Vec<f32> = data.iter().copied().filter(|v| v.is_finite()).collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_unstable_by(f32::total_cmp);
+
+        let percentile = self.percentile.clamp(0.5, 1.0);
+        let last = (values.len() - 1) as f32;
+        let low = values[((1.0 - percentile) * last).round() as usize];
+        let high = values[(percentile * last).round() as usize];
+
+        // Don't let a flat trace collapse the range
+        let span = (high - low).max(1.0);
+        let headroom = span * self.headroom.max(0.0);
+        Some(((low - headroom).floor(), (high + headroom).ceil()))
+    }
+}
+
+/// Smoothed limits, updated with every frame
+#[derive(Clone, Debug, Default)]
+pub struct AutoRange {
+    /// Current limits and the time in seconds they were computed at
+    state: Option<(f32, f32, f64)>,
+}
+
+impl AutoRange {
+    /// Move the limits towards those of `data` at time `now` in seconds and return them.
+    /// `None` if there never were any finite values.
+    pub fn update(
+        &mut self,
+        data: &[f32],
+        settings: &AutoRangeSettings,
+        now: f64,
+    ) -> Option<(f32, f32)> {
+        self.update_limits(settings.limits(data), settings, now)
+    }
+
+    /// Like [`AutoRange::update`], but with the target limits already computed by
+    /// [`AutoRangeSettings::limits`]
+    pub fn update_limits(
+        &mut self,
+        target: Option<(f32, f32)>,
+        settings: &AutoRangeSettings,
+        now: f64,
+    ) -> Option<(f32, f32)> {
+        let Some((target_min, target_max)) = target else {
+            return self.state.map(|(min, max, _)| (min.floor(), max.ceil()));
+        };
+
+        let (min, max) = match self.state {
+            Some((min, max, time)) => {
+                // Only shrink if the data left at least half of the headroom unused, otherwise
+                // small fluctuations would keep the range in motion
+                let hysteresis = (target_max - target_min) * settings.headroom / 2.0;
+                let decay = if settings.decay_time > 0.0 {
+                    (-(now - time) as f32 / settings.decay_time).exp()
+                } else {
+                    0.0
+                };
+                let min = if target_min <= min || target_min - min < hysteresis {
+                    target_min.min(min)
+                } else {
+                    target_min + (min - target_min) * decay
+                };
+                let max = if target_max >= max || max - target_max < hysteresis {
+                    target_max.max(max)
+                } else {
+                    target_max + (max - target_max) * decay
+                };
+                (min, max)
+            }
+            None => (target_min, target_max),
+        };
+
+        self.state = Some((min, max, now));
+        Some((min.floor(), max.ceil()))
+    }
+}
+
+thread_local! {
+    /// Auto-range of the spectrum plot, which is always prepared on the GUI thread
+    static SPECTRUM_RANGE: RefCell<AutoRange> = RefCell::new(AutoRange::default());
+}
+
+/// Update the auto-range of the spectrum plot, see [`AutoRange::update_limits`].
+///
+/// The limits are rounded outwards to the step between the y-axis labels. While the range
+/// shrinks, it then only changes every few frames, and the cached background of the plot is
+/// reused in between.
+pub fn spectrum_range(
+    target: Option<(f32, f32)>,
+    settings: &AutoRangeSettings,
+) -> Option<(f32, f32)> {
+    SPECTRUM_RANGE
+        .with_borrow_mut(|range| range.update_limits(target, settings, now()))
+        .map(|limits| snap_to_labels(limits, super::Y_LABELS))
+}
+
+/// Round `min` and `max` outwards to a multiple of the step between at most `labels` axis
+/// labels, see [`super::nice_step`]
+fn snap_to_labels((min, max): (f32, f32), labels: usize) -> (f32, f32) {
+    match super::nice_step(max - min, labels) {
+        Some(step) => ((min / step).floor() * step, (max / step).ceil() * step),
+        None => (min, max),
+    }
+}
+
+/// Monotonic time in seconds, `Instant` is not available in the browser
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    use std::{sync::OnceLock, time::Instant};
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_to_label_steps() {
+        assert_eq!(snap_to_labels((-73.0, 12.0), 10), (-80.0, 20.0));
+        assert_eq!(snap_to_labels((-41.0, -3.0), 10), (-45.0, 0.0));
+        // Shrinking a little does not change the snapped range
+        assert_eq!(snap_to_labels((-71.4, 11.2), 10), (-80.0, 20.0));
+        assert_eq!(snap_to_labels((5.0, 5.0), 10), (5.0, 5.0));
+    }
+}
@@ -0,0 +1,168 @@
+//! Several spectra plotted together, e.g., from different branches of a flowgraph.
+//!
+//! The channels are listed in `PlotMeta.channels`, where the user toggles their visibility and
+//! shifts them along the y-axis. Sources name their channels with [`define`]. Channels that are
+//! plotted without being defined get a generic name.
+//!
+//! Like the history, the frames are recorded on the GUI event loop.
+
+use std::cell::RefCell;
+
+use slint::{ComponentHandle, Model, ModelRc, VecModel};
+
+use super::{
+    config::ChannelStyle,
+    theme::{self, PlotTheme},
+};
+use crate::{MainWindow, PlotChannel, PlotMeta};
+
+thread_local! {
+    static LAST_FRAMES: RefCell<Vec<Vec<f32>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Name the channels of a source. Visibility and offsets are kept by index, so they survive
+/// restarting the source.
+pub fn define(app: &MainWindow, names: &[String]) {
+    let theme = PlotTheme::from_app(app);
+    let current = channels(app);
+    let channels: Vec<PlotChannel> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| PlotChannel {
+            name: name.as_str().into(),
+            ..current
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| new_channel(&theme, index))
+        })
+        .collect();
+    set_channels(app, channels);
+}
+
+/// Show or hide the channel at `index`
+pub fn set_visible(app: &MainWindow, index: usize, visible: bool) {
+    update(app, index, |channel| channel.visible = visible);
+}
+
+/// Shift the channel at `index` by `offset` along the y-axis
+pub fn set_offset(app: &MainWindow, index: usize, offset: i32) {
+    update(app, index, |channel| channel.offset = offset);
+}
+
+/// Visibility and offset of all channels, in the order of the channels
+pub fn settings(app: &MainWindow) -> (Vec<bool>, Vec<i32>) {
+    channels(app)
+        .iter()
+        .map(|channel| (channel.visible, channel.offset))
+        .unzip()
+}
+
+/// Restore visibility and offsets stored by [`settings`], adding channels that do not exist yet
+pub fn restore(app: &MainWindow, visible: &[bool], offsets: &[i32]) {
+    let theme = PlotTheme::from_app(app);
+    let mut channels = channels(app);
+    let count = visible.len().max(offsets.len()).max(channels.len());
+    for index in 0..count {
+        if index == channels.len() {
+            channels.push(new_channel(&theme, index));
+        }
+        let channel = &mut channels[index];
+        channel.visible = visible.get(index).copied().unwrap_or(true);
+        channel.offset = offsets.get(index).copied().unwrap_or(0);
+    }
+    set_channels(app, channels);
+}
+
+/// Update the colors of the legend in the GUI to `theme`
+pub fn recolor(app: &MainWindow, theme: &PlotTheme) {
+    let channels = channels(app)
+        .into_iter()
+        .enumerate()
+        .map(|(index, channel)| PlotChannel {
+            color: theme::to_slint_color(theme.channel_color(index)),
+            ..channel
+        })
+        .collect();
+    set_channels(app, channels);
+}
+
+/// Styles of all channels listed in the GUI, see [`super::config::PlotConfig::channels`]
+pub fn styles(app: &MainWindow) -> Vec<ChannelStyle> {
+    channels(app)
+        .iter()
+        .map(|channel| ChannelStyle {
+            name: channel.name.to_string(),
+            visible: channel.visible,
+            offset: channel.offset as f32,
+        })
+        .collect()
+}
+
+/// Keep `frames` for snapshots and list channels that have not been defined
+pub(super) fn record(app: &MainWindow, frames: &[&[f32]]) {
+    LAST_FRAMES.with_borrow_mut(|last| {
+        *last = frames.iter().map(|frame| frame.to_vec()).collect();
+    });
+
+    let mut channels = channels(app);
+    if channels.len() < frames.len() {
+        let theme = PlotTheme::from_app(app);
+        for index in channels.len()..frames.len() {
+            channels.push(new_channel(&theme, index));
+        }
+        set_channels(app, channels);
+    }
+}
+
+/// Frames of all channels most recently plotted, the live spectrum first
+pub fn last_frames() -> Vec<Vec<f32>> {
+    LAST_FRAMES.with_borrow(Clone::clone)
+}
+
+/// Number of bins of the longest frame most recently plotted
+pub fn bins() -> usize {
+    LAST_FRAMES.with_borrow(|last| last.iter().map(Vec::len).max().unwrap_or(0))
+}
+
+fn new_channel(theme: &PlotTheme, index: usize) -> PlotChannel {
+    let style = ChannelStyle::new(index);
+    PlotChannel {
+        name: style.name.into(),
+        color: theme::to_slint_color(theme.channel_color(index)),
+        visible: style.visible,
+        offset: style.offset as i32,
+    }
+}
+
+fn update(app: &MainWindow, index: usize, change: impl FnOnce(&mut PlotChannel)) {
+    let mut channels = channels(app);
+    if let Some(channel) = channels.get_mut(index) {
+        change(channel);
+        set_channels(app, channels);
+    }
+}
+
+fn channels(app: &MainWindow) -> Vec<PlotChannel> {
+    app.global::<PlotMeta>().get_channels().iter().collect()
+}
+
+/// Update the list in the GUI in place. Replacing the model would recreate the rows of the
+/// legend, so the spin box of an offset loses its focus while the user edits it.
+fn set_channels(app: &MainWindow, channels: Vec<PlotChannel>) {
+    let meta = app.global::<PlotMeta>();
+    let model = meta.get_channels();
+    let Some(rows) = model.as_any().downcast_ref::<VecModel<PlotChannel>>() else {
+        meta.set_channels(ModelRc::new(VecModel::from(channels)));
+        return;
+    };
+    for (index, channel) in channels.iter().enumerate() {
+        match rows.row_data(index) {
+            Some(row) if row == *channel => {}
+            Some(_) => rows.set_row_data(index, channel.clone()),
+            None => rows.push(channel.clone()),
+        }
+    }
+    while rows.row_count() > channels.len() {
+        rows.remove(rows.row_count() - 1);
+    }
+}
@@ -0,0 +1,168 @@
+//! Inputs and outputs of the spectrum renderer, independent of the GUI.
+
+use slint::{ComponentHandle, Model};
+
+use super::{auto_range::AutoRangeSettings, channels, theme::PlotTheme};
+use crate::{MainWindow, PlotMeta};
+
+/// How the y-axis limits are chosen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YRange {
+    /// Fixed lower and upper limit
+    Fixed(f32, f32),
+    /// Fit the limits to each frame with [`PlotConfig::auto_range`]
+    Fit,
+}
+
+/// How a reference trace is combined with the live spectrum
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReferenceMode {
+    /// Draw the reference as a separate trace
+    #[default]
+    Overlay,
+    /// Plot the live spectrum minus the reference
+    Difference,
+}
+
+/// How one of several spectra plotted together is drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChannelStyle {
+    /// Name in the legend
+    pub name: String,
+    pub visible: bool,
+    /// Added to all levels of the channel, e.g., to separate overlapping traces
+    pub offset: f32,
+}
+
+impl ChannelStyle {
+    /// Visible channel without offset, named by its position
+    pub fn new(index: usize) -> Self {
+        Self {
+            name: format!("Channel {}", index + 1),
+            visible: true,
+            offset: 0.0,
+        }
+    }
+}
+
+/// Everything that determines how a spectrum is plotted
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotConfig {
+    /// Image size in pixels
+    pub width: u32,
+    pub height: u32,
+    /// Range of bins on the x-axis, all bins if `None`
+    pub x_range: Option<(f32, f32)>,
+    pub y_range: YRange,
+    /// Used to fit the y-range and for [`PlotStats::fit_range`]
+    pub auto_range: AutoRangeSettings,
+    pub theme: PlotTheme,
+    /// Reference trace, ignored unless it has as many bins as the spectrum
+    pub reference: Option<Vec<f32>>,
+    pub reference_mode: ReferenceMode,
+    /// Styles of the channels by index, see [`PlotConfig::channel`]
+    pub channels: Vec<ChannelStyle>,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            x_range: None,
+            y_range: YRange::Fit,
+            auto_range: AutoRangeSettings::default(),
+            theme: PlotTheme::default(),
+            reference: None,
+            reference_mode: ReferenceMode::default(),
+            channels: Vec::new(),
+        }
+    }
+}
+
+impl PlotConfig {
+    /// Configuration currently set up in the GUI, sized like the plot image.
+    ///
+    /// With auto-ranging enabled, the y-range is fitted to each frame without smoothing, which is
+    /// up to the caller.
+    pub fn from_app(app: &MainWindow) -> Self {
+        let meta = app.global::<PlotMeta>();
+
+        let reference: Vec<f32> = meta.get_reference().iter().collect();
+        let reference_mode = if meta.get_reference_difference() {
+            ReferenceMode::Difference
+        } else {
+            ReferenceMode::Overlay
+        };
+        // A hidden reference only matters for the difference
+        let reference = (!reference.is_empty()
+            && (meta.get_show_reference() || reference_mode == ReferenceMode::Difference))
+            .then_some(reference);
+
+        let y_range = if app.get_zoomed() {
+            YRange::Fixed(app.get_zoom_y_min(), app.get_zoom_y_max())
+        } else if app.get_y_auto_update() {
+            YRange::Fit
+        } else {
+            YRange::Fixed(app.get_y_axis_min() as f32, app.get_y_axis_max() as f32)
+        };
+
+        Self {
+            width: app.get_plot_width() as u32,
+            height: app.get_plot_height() as u32,
+            x_range: app
+                .get_zoomed()
+                .then(|| (app.get_zoom_x_min(), app.get_zoom_x_max())),
+            y_range,
+            auto_range: AutoRangeSettings::from_app(app),
+            theme: PlotTheme::from_app(app),
+            reference,
+            reference_mode,
+            channels: channels::styles(app),
+        }
+    }
+
+    /// Style of the channel at `index`, channels without a style are drawn like
+    /// [`ChannelStyle::new`]
+    pub fn channel(&self, index: usize) -> ChannelStyle {
+        self.channels
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| ChannelStyle::new(index))
+    }
+}
+
+/// Statistics of the plotted bins, i.e., only the visible ones and after subtracting a reference.
+///
+/// With several channels, the statistics are those of the first visible one, while
+/// [`PlotStats::fit_range`] fits all visible channels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlotStats {
+    /// Smallest finite level
+    pub min: Option<f32>,
+    /// Largest finite level
+    pub max: Option<f32>,
+    /// Bin of the largest finite level
+    pub peak_bin: Option<usize>,
+    /// Mean of all finite levels
+    pub mean: Option<f32>,
+    /// y-axis limits fitting the data, see [`AutoRangeSettings::limits`]
+    pub fit_range: Option<(f32, f32)>,
+}
+
+impl PlotStats {
+    /// Statistics of `data`, whose first bin is `first_bin`, with `fit_range` computed from
+    /// `auto_range`
+    pub fn new(data: &[f32], first_bin: usize, auto_range: &AutoRangeSettings) -> Self {
+        let finite = || data.iter().enumerate().filter(|(_, v)| v.is_finite());
+        let count = finite().count();
+        let peak = finite().max_by(|(_, a), (_, b)| a.total_cmp(b));
+        Self {
+            min: finite().map(|(_, v)| *v).reduce(f32::min),
+            max: peak.map(|(_, v)| *v),
+            peak_bin: peak.map(|(bin, _)| first_bin + bin),
+            mean: (count > 0).then(|| finite().map(|(_, v)| v).sum::<f32>() / count as f32),
+            fit_range: auto_range.limits(data),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::{
+    theme::PlotTheme,
+    worker::{self, BackgroundKey, RenderJob, Target},
+    Chart,
+};
+use crate::{error::Result, MainWindow};
+use log::debug;
+use plotters::{coord::Shift, prelude::*};
+use std::collections::VecDeque;
+
+/// Constellation diagram with point persistence.
+///
+/// Every call to [`Constellation::push`] adds one frame of points. Older frames are kept
+/// according to the persistence setting of the GUI and fade out the older they get.
+#[derive(Default)]
+pub struct Constellation {
+    frames: VecDeque<Vec<(f32, f32)>>,
+}
+
+impl Constellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new frame of complex samples given as `(I, Q)` tuples.
+    ///
+    /// The settings `constellation-points`, `constellation-persistence` and
+    /// `constellation-decimation` are read from the GUI.
+    /// A decimation of `n` only keeps every `n`-th sample, so that one point per symbol remains
+    /// if `n` matches the samples per symbol.
+    pub fn push(&mut self, samples: &[(f32, f32)], app: &MainWindow) {
+        let points = app.get_constellation_points().max(1) as usize;
+        let persistence = app.get_constellation_persistence().max(1) as usize;
+        let decimation = app.get_constellation_decimation().max(1) as usize;
+
+        self.frames.push_back(
+            samples
+                .iter()
+                .step_by(decimation)
+                .take(points)
+                .cloned()
+                .collect(),
+        );
+
+        while self.frames.len() > persistence {
+            self.frames.pop_front();
+        }
+    }
+
+    /// Remove all stored frames
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Render all stored frames and show them as `constellation-frame` in the GUI, the newest
+    /// frame being fully opaque.
+    ///
+    /// Like [`render_plot`](super::render_plot), this happens asynchronously off the GUI thread
+    /// and is skipped while the diagram has no size.
+    pub fn render(&self, app: &MainWindow) -> Result<()> {
+        debug!("Start Constellation Plotting");
+
+        let width = app.get_constellation_width() as u32;
+        let height = app.get_constellation_height() as u32;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // Symmetric limits for both axes, so the constellation is not distorted.
+        // Rounded up to a power of two, so the (cached) background rarely changes.
+        let limit = (self
+            .frames
+            .iter()
+            .flatten()
+            .map(|(i, q)| i.abs().max(q.abs()))
+            .filter(|v| v.is_finite())
+            .fold(0.0f32, f32::max)
+            .max(f32::EPSILON)
+            * 1.1)
+            .log2()
+            .ceil()
+            .exp2();
+
+        let theme = PlotTheme::from_app(app);
+        let background_theme = theme.clone();
+        let frames = self.frames.clone();
+
+        worker::submit(
+            app,
+            Target::Constellation,
+            RenderJob {
+                width,
+                height,
+                background: theme.background,
+                background_key: BackgroundKey {
+                    width,
+                    height,
+                    ranges: [-limit, limit, -limit, limit],
+                    theme: theme.clone(),
+                },
+                draw_background: Box::new(move |root| {
+                    let theme = background_theme;
+                    chart(root, limit)?
+                        .configure_mesh()
+                        .bold_line_style(theme.bold_line_style())
+                        .light_line_style(theme.light_line_style())
+                        .axis_style(theme.axis_style())
+                        .x_labels(5)
+                        .x_label_style(theme.label_style())
+                        .x_label_formatter(&|x| format!("{:.1}", x))
+                        .x_desc("I")
+                        .y_labels(5)
+                        .y_label_style(theme.label_style())
+                        .y_label_formatter(&|y| format!("{:.1}", y))
+                        .y_desc("Q")
+                        .axis_desc_style(theme.label_style())
+                        .draw()?;
+                    Ok(())
+                }),
+                draw: Box::new(move |root| {
+                    let mut chart = chart(root, limit)?;
+
+                    // Oldest frames first, so newer points are drawn on top
+                    let n = frames.len();
+                    for (age, frame) in frames.iter().enumerate() {
+                        let alpha = (age + 1) as f64 / n as f64;
+                        chart.draw_series(frame.iter().map(|&(i, q)| {
+                            let color = theme.trace.mix(alpha).filled();
+                            Circle::new((i as f64, q as f64), 2, color)
+                        }))?;
+                    }
+                    Ok(None)
+                }),
+            },
+        )
+    }
+}
+
+/// Square chart from `-limit` to `limit` on both axes
+fn chart<DB: DrawingBackend>(root: &DrawingArea<DB, Shift>, limit: f32) -> Result<Chart<'_, DB>> {
+    let limit = limit as f64;
+    let chart = ChartBuilder::on(root)
+        .x_label_area_size(28)
+        .y_label_area_size(28)
+        .margin(20)
+        .build_cartesian_2d(-limit..limit, -limit..limit)?;
+    Ok(chart)
+}
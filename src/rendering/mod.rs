@@ -1,56 +1,507 @@
-use crate::{MainWindow, PlotMeta};
+use crate::{
+    error::{Error, Result},
+    ChartArea, MainWindow, PlotMeta,
+};
 use log::{debug, trace};
-use plotters::prelude::*;
-use slint::{ComponentHandle, Image};
+use plotters::{
+    coord::{cartesian::Cartesian2d, types::RangedCoordf64, Shift},
+    prelude::*,
+};
+use slint::{ComponentHandle, Image, ModelRc, VecModel};
+use snapshot::SnapshotFormat;
+use std::sync::Arc;
+use worker::{BackgroundKey, RenderJob, Target};
 
-// This is a bitmap backend without text for WASM compatibility
-#[cfg(all(target_arch = "wasm32", not(feature = "svg")))]
+// This is a bitmap backend with an embedded font for WASM compatibility
+#[cfg(target_arch = "wasm32")]
 mod wasm_backend;
 
-const PLOT_LINE_COLOR: RGBColor = RGBColor(0, 175, 255);
+/// Constellation (I vs. Q) diagram
+pub mod constellation;
+
+/// Export the current plot as PNG or SVG
+pub mod snapshot;
+
+/// Mouse zoom and pan
+pub mod view;
+
+/// Colors and fonts
+pub mod theme;
+use theme::PlotTheme;
+
+// Render plots off the GUI thread
+mod worker;
+
+// Draw plots with Slint elements instead of plotters
+mod native;
+
+/// Smoothed automatic y-axis limits
+pub mod auto_range;
+
+/// Several spectra plotted together
+pub mod channels;
+
+/// GUI-independent configuration and statistics of the renderer
+pub mod config;
+use config::{PlotConfig, PlotStats, ReferenceMode, YRange};
+
+/// Plotters backend selected by the enabled features
+#[cfg(feature = "svg")]
+type Backend<'a> = plotters_svg::SVGBackend<'a>;
+#[cfg(all(target_arch = "wasm32", not(feature = "svg")))]
+type Backend<'a> = wasm_backend::BackendWithEmbeddedFont<BitMapBackend<'a>>;
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "svg")))]
+type Backend<'a> = BitMapBackend<'a>;
+
+/// Largest number of labels on the y-axis of the spectrum plot
+const Y_LABELS: usize = 10;
 
-/// Render the FFT `data`  into an [`Image`]
-pub fn render_plot(data: &[f32], app: &MainWindow) -> Image {
+/// Distance between about `count` axis labels over `span` at "nice" values, i.e., 1, 2 or 5 times
+/// a power of ten like the steps plotters picks. `None` if `span` is empty or not finite.
+fn nice_step(span: f32, count: usize) -> Option<f32> {
+    if !(span.is_finite() && span > 0.0) {
+        return None;
+    }
+    let rough = span / count as f32;
+    let magnitude = 10f32.powf(rough.log10().floor());
+    Some(
+        [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= rough)
+            .unwrap_or(10.0 * magnitude),
+    )
+}
+
+/// Render the FFT `data` and show it as `plot-frame` in the GUI
+///
+/// This adapts the GUI to [`SpectrumPlot`]: the configuration is read from the GUI and the
+/// statistics of the frame are written to `PlotMeta`. Only the bins within the zoomed x-range of
+/// the GUI are drawn.
+/// Rendering happens off the GUI thread, so the image is updated asynchronously. If frames
+/// arrive faster than they can be rendered, the outdated ones are skipped.
+/// With `native-plot` enabled in the GUI, the plot is drawn by Slint elements instead.
+/// Frames are skipped while the plot has no size, e.g., while the window is minimized.
+pub fn render_plot(data: &[f32], app: &MainWindow) -> Result<()> {
+    render_plot_channels(&[data], app)
+}
+
+/// Render the FFT `frames` of several channels on one plot, like [`render_plot`]
+///
+/// The first frame is the live spectrum, which is stored as last frame and recorded in the
+/// history. Each channel is drawn as set up in `PlotMeta.channels`, see [`channels`].
+pub fn render_plot_channels(frames: &[&[f32]], app: &MainWindow) -> Result<()> {
     debug!("Start Plotting");
 
-    // Obtain dimensions of the plot image
-    let width = app.get_plot_width() as u32;
-    let height = app.get_plot_height() as u32;
+    // Keep the live spectrum around, so it can be stored as reference trace or exported
+    let data = frames.first().copied().unwrap_or_default();
+    let meta = app.global::<PlotMeta>();
+    meta.set_last_frame(ModelRc::new(VecModel::from(data.to_vec())));
+    crate::history::record(data);
+    channels::record(app, frames);
 
-    assert!(width > 0, "Width must be >0");
-    assert!(height > 0, "Height must be >0");
+    draw_channels(frames, app)
+}
 
-    trace!("width, height = {}, {}", width, height);
+/// Render the most recently plotted frames again, e.g., after zooming while the plot is disabled
+pub fn redraw(app: &MainWindow) -> Result<()> {
+    let frames = channels::last_frames();
+    if frames.is_empty() {
+        return Ok(());
+    }
+    let frames: Vec<&[f32]> = frames.iter().map(Vec::as_slice).collect();
+    draw_channels(&frames, app)
+}
 
-    // Compute the y axis min and max values
-    let data_y_min = data.iter().cloned().reduce(f32::min).unwrap().ceil();
-    let data_y_max = data.iter().cloned().reduce(f32::max).unwrap().floor();
+/// Draw `frames` as configured in the GUI, without recording them
+fn draw_channels(frames: &[&[f32]], app: &MainWindow) -> Result<()> {
+    let meta = app.global::<PlotMeta>();
+    let config = PlotConfig::from_app(app);
+    let mut plot = SpectrumPlot::new(frames, &config);
 
     // Store to global metadata for (potential) later use
-    app.global::<PlotMeta>().set_min_value(data_y_min);
-    app.global::<PlotMeta>().set_max_value(data_y_max);
-
-    // Set the y-axis limits either automatically or to the user-defined value
-    let (y_min, y_max) = if app.get_y_auto_update() {
-        (data_y_min, data_y_max)
-    } else {
-        (app.get_y_axis_min() as f32, app.get_y_axis_max() as f32)
-    };
+    if let Some((min, max)) = plot.stats.fit_range {
+        meta.set_min_value(min);
+        meta.set_max_value(max);
+    }
+
+    // Auto-ranging in the GUI is smoothed over consecutive frames
+    if config.y_range == YRange::Fit {
+        if let Some(range) = auto_range::spectrum_range(plot.stats.fit_range, &config.auto_range) {
+            plot.set_y_range(range);
+        }
+    }
+
+    if app.get_native_plot() {
+        native::show(&plot, app);
+        return Ok(());
+    }
+
+    let (width, height) = (config.width, config.height);
+    if width == 0 || height == 0 {
+        trace!("Plot has no size, skipping frame");
+        return Ok(());
+    }
+    let plot = Arc::new(plot);
+    let background_plot = plot.clone();
+    worker::submit(
+        app,
+        Target::Spectrum,
+        RenderJob {
+            width,
+            height,
+            background: plot.theme.background,
+            background_key: BackgroundKey {
+                width,
+                height,
+                ranges: [plot.x_min, plot.x_max, plot.y_min, plot.y_max],
+                theme: plot.theme.clone(),
+            },
+            draw_background: Box::new(move |root| background_plot.draw_background(root)),
+            draw: Box::new(move |root| plot.draw_traces(root).map(Some)),
+        },
+    )
+}
+
+/// A spectrum rendered by [`render`]
+#[derive(Clone, Debug)]
+pub struct RenderedPlot {
+    /// Encoded image file
+    pub image: Vec<u8>,
+    /// Where the chart was placed in the image
+    pub chart: ChartArea,
+    pub stats: PlotStats,
+}
+
+/// Render the FFT `data` as set up in `config` and encode it as `format`.
+///
+/// This neither needs nor changes a GUI, so it can be used without a window, e.g., from the
+/// command line or in tests. Unlike in the GUI, auto-ranged y-limits are not smoothed.
+pub fn render(data: &[f32], config: &PlotConfig, format: SnapshotFormat) -> Result<RenderedPlot> {
+    render_channels(&[data], config, format)
+}
+
+/// Render the FFT `frames` of several channels on one plot, like [`render`]
+pub fn render_channels(
+    frames: &[&[f32]],
+    config: &PlotConfig,
+    format: SnapshotFormat,
+) -> Result<RenderedPlot> {
+    let plot = SpectrumPlot::new(frames, config);
+    let (image, chart) = snapshot::encode(&plot, format, config.width, config.height)?;
+    Ok(RenderedPlot {
+        image,
+        chart,
+        stats: plot.stats,
+    })
+}
+
+/// Chart with `f64` coordinates on both axes
+type Chart<'a, DB> = ChartContext<'a, DB, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+/// Spectrum of one channel of a [`SpectrumPlot`]
+struct Trace {
+    /// Visible bins only, starting at `first_bin`, shifted by the offset of the channel
+    data: Vec<f32>,
+    color: RGBColor,
+    /// The area below the live spectrum is filled, other channels are drawn as lines
+    filled: bool,
+    /// Entry in the legend, which is only drawn for several channels
+    label: Option<String>,
+}
+
+/// Spectrum frames of all visible channels including everything that is drawn on top of them,
+/// ready to be drawn onto any plotters backend
+struct SpectrumPlot {
+    traces: Vec<Trace>,
+    /// Visible bins only, starting at `first_bin`
+    reference: Option<Vec<f32>>,
+    first_bin: usize,
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+    y_max: f32,
+    theme: PlotTheme,
+    stats: PlotStats,
+}
+
+impl SpectrumPlot {
+    /// Combine the FFT `frames` of all channels with the reference trace and the axis ranges of
+    /// `config`. The first frame is the live spectrum.
+    fn new(frames: &[&[f32]], config: &PlotConfig) -> Self {
+        // A reference trace is only usable if it has as many bins as the live data
+        let live_bins = frames.first().map_or(0, |data| data.len());
+        let reference = config.reference.as_ref().filter(|r| r.len() == live_bins);
+
+        // In difference mode the reference is subtracted from all channels with as many bins
+        // and not drawn separately
+        let (difference, reference) = match config.reference_mode {
+            ReferenceMode::Difference => (reference, None),
+            ReferenceMode::Overlay => (None, reference),
+        };
+
+        // x-axis limits either zoomed or set to match how `enumerate` generates the x values
+        let bins = frames.iter().map(|data| data.len()).max().unwrap_or(0);
+        let last_bin = bins.saturating_sub(1) as f32;
+        // A zoomed range outside of the bins is shifted back, keeping its span where possible
+        let (x_min, x_max) = match config.x_range {
+            Some((x_min, x_max)) => {
+                let span = (x_max - x_min).clamp(0.0, last_bin);
+                let x_min = x_min.clamp(0.0, last_bin - span);
+                (x_min, x_min + span)
+            }
+            None => (0.0, last_bin),
+        };
+
+        // Only keep the visible bins plus one on either side, so the trace reaches the edges
+        let first_bin = (x_min.floor() as usize).min(bins);
+        let end = (x_max.ceil() as usize + 1).clamp(first_bin, bins);
+        let visible = |len: usize| first_bin.min(len)..end.min(len);
+        let reference = reference.map(|r| r[visible(r.len())].to_vec());
 
-    // x-axis limits set to match how `enumerate` generates the x values later on
-    let x_min = 0;
-    let x_max = data.len() - 1;
+        let legend = frames.len() > 1;
+        let traces: Vec<Trace> = frames
+            .iter()
+            .enumerate()
+            .filter_map(|(index, data)| {
+                let style = config.channel(index);
+                if !style.visible {
+                    return None;
+                }
+                let range = visible(data.len());
+                let difference = difference.filter(|r| r.len() == data.len());
+                let data = data[range.clone()]
+                    .iter()
+                    .zip(range)
+                    .map(|(level, bin)| level - difference.map_or(0.0, |r| r[bin]) + style.offset)
+                    .collect();
+                Some(Trace {
+                    data,
+                    color: config.theme.channel_color(index),
+                    filled: index == 0,
+                    label: legend.then_some(style.name),
+                })
+            })
+            .collect();
+
+        // plotters cannot map an empty range, e.g., without any bins
+        let x_max = if x_max > x_min { x_max } else { x_min + 1.0 };
+
+        let first = traces.first().map_or(&[][..], |trace| &trace.data[..]);
+        let mut stats = PlotStats::new(first, first_bin, &config.auto_range);
+        if traces.len() > 1 {
+            let all: Vec<f32> = traces.iter().flat_map(|t| t.data.iter().copied()).collect();
+            stats.fit_range = config.auto_range.limits(&all);
+        }
+
+        // Set the y-axis limits either to the configured values or fitted to the data
+        let (y_min, y_max) = match config.y_range {
+            YRange::Fixed(y_min, y_max) => (y_min, y_max),
+            YRange::Fit => stats.fit_range.unwrap_or((0.0, 1.0)),
+        };
+        let y_max = if y_max > y_min { y_max } else { y_min + 1.0 };
+
+        Self {
+            traces,
+            reference,
+            first_bin,
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+            theme: config.theme.clone(),
+            stats,
+        }
+    }
+
+    /// Replace the y-axis limits, e.g., with smoothed ones
+    fn set_y_range(&mut self, (y_min, y_max): (f32, f32)) {
+        self.y_min = y_min;
+        self.y_max = y_max;
+    }
+
+    /// Draw axes, labels and all traces onto `root` and return where the chart was placed
+    fn draw<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<ChartArea> {
+        self.draw_background(root)?;
+        self.draw_traces(root)
+    }
+
+    /// Build the chart, which is identical for the background and the traces
+    fn chart<'a, DB: DrawingBackend>(
+        &self,
+        root: &'a DrawingArea<DB, Shift>,
+    ) -> Result<Chart<'a, DB>> {
+        let chart = ChartBuilder::on(root)
+            .x_label_area_size(28)
+            .y_label_area_size(28)
+            .margin(20)
+            .build_cartesian_2d(
+                (self.x_min as f64)..(self.x_max as f64),
+                (self.y_min as f64)..(self.y_max as f64),
+            )?;
+        Ok(chart)
+    }
+
+    /// Draw the mesh, axes and labels, which only change with the size, ranges or theme
+    fn draw_background<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<()> {
+        // Configure the chart
+        self.chart(root)?
+            .configure_mesh()
+            //.disable_mesh() // Disable mesh for faster SVG rendering
+            .bold_line_style(self.theme.bold_line_style())
+            .light_line_style(self.theme.light_line_style())
+            .axis_style(self.theme.axis_style())
+            .x_labels(20)
+            .x_label_style(self.theme.label_style())
+            .x_label_formatter(&|x| format!("{}", x))
+            .y_labels(Y_LABELS)
+            .y_label_style(self.theme.label_style())
+            .y_label_formatter(&|y| format!("{}", y))
+            .draw()?;
+        Ok(())
+    }
+
+    /// Draw all traces onto `root` and return where the chart was placed
+    fn draw_traces<DB: DrawingBackend>(&self, root: &DrawingArea<DB, Shift>) -> Result<ChartArea> {
+        let mut chart = self.chart(root)?;
+        let legend = self.traces.iter().any(|trace| trace.label.is_some());
+
+        for trace in &self.traces {
+            // Setting to 2 can cause plotters to hang see https://github.com/plotters-rs/plotters/issues/562
+            let style = ShapeStyle::from(trace.color).stroke_width(1);
+            let series = if trace.filled {
+                chart.draw_series(
+                    AreaSeries::new(
+                        self.points(&trace.data),
+                        -1.0,
+                        trace.color.mix(self.theme.fill_opacity),
+                    )
+                    .border_style(style),
+                )?
+            } else {
+                chart.draw_series(LineSeries::new(self.points(&trace.data), style))?
+            };
+            if let Some(label) = &trace.label {
+                series
+                    .label(label)
+                    .legend(move |(x, y)| legend_line(x, y, style));
+            }
+        }
+
+        // Overlay the reference trace in a different color
+        if let Some(reference) = &self.reference {
+            let style = ShapeStyle::from(self.theme.reference).stroke_width(1);
+            let series = chart.draw_series(LineSeries::new(self.points(reference), style))?;
+            if legend {
+                series
+                    .label("Reference")
+                    .legend(move |(x, y)| legend_line(x, y, style));
+            }
+        }
+
+        if legend {
+            chart
+                .configure_series_labels()
+                .position(SeriesLabelPosition::UpperRight)
+                .background_style(self.theme.background.mix(0.8))
+                .border_style(self.theme.axis_style())
+                .label_font(self.theme.label_style())
+                .draw()?;
+        }
+
+        // Pixel range of the plotting area relative to the whole image
+        let (width, height) = root.dim_in_pixel();
+        let (x_pixels, y_pixels) = chart.plotting_area().get_pixel_range();
+        Ok(ChartArea {
+            left: x_pixels.start as f32 / width as f32,
+            right: x_pixels.end as f32 / width as f32,
+            top: y_pixels.start as f32 / height as f32,
+            bottom: y_pixels.end as f32 / height as f32,
+            x_min: self.x_min,
+            x_max: self.x_max,
+            y_min: self.y_min,
+            y_max: self.y_max,
+        })
+    }
+
+    /// Chart coordinates of the visible bins of `trace`. Non-finite levels cannot be drawn and
+    /// are left out.
+    fn points<'a>(&self, trace: &'a [f32]) -> impl Iterator<Item = (f64, f64)> + 'a {
+        let first_bin = self.first_bin;
+        trace
+            .iter()
+            .enumerate()
+            .filter(|(_, y)| y.is_finite())
+            .map(move |(x, y)| ((first_bin + x) as f64, *y as f64))
+    }
+}
+
+/// Short line in the color of a trace, marking its entry in the legend
+fn legend_line(x: i32, y: i32, style: ShapeStyle) -> PathElement<(i32, i32)> {
+    PathElement::new(vec![(x, y), (x + 20, y)], style)
+}
+
+/// A rendered plot that can be sent between threads, as opposed to an [`Image`]
+#[derive(Clone)]
+enum RenderedImage {
+    #[cfg_attr(feature = "svg", allow(dead_code))]
+    Pixels(slint::SharedPixelBuffer<slint::Rgb8Pixel>),
+    #[cfg_attr(not(feature = "svg"), allow(dead_code))]
+    Svg(String),
+}
+
+impl RenderedImage {
+    /// Convert into an [`Image`], which has to happen on the GUI thread
+    fn into_image(self) -> Result<Image> {
+        match self {
+            Self::Pixels(pixel_buffer) => Ok(Image::from_rgb8(pixel_buffer)),
+            Self::Svg(svg) => Image::load_from_svg_data(svg.as_bytes())
+                .map_err(|_| Error::Image("cannot load rendered SVG".to_string())),
+        }
+    }
+}
+
+/// Create a drawing area of `width` x `height` pixels filled with `background` on the backend
+/// selected by the features, let `draw` paint onto it and return the result
+fn render_to_image<R>(
+    width: u32,
+    height: u32,
+    background: RGBColor,
+    draw: impl for<'a> FnOnce(&DrawingArea<Backend<'a>, Shift>) -> Result<R>,
+) -> Result<(RenderedImage, R)> {
+    render_over(None, width, height, |root| {
+        root.fill(&background)?;
+        draw(root)
+    })
+}
+
+/// Let `draw` paint onto a copy of `base`, which must have been rendered at the same size, or
+/// onto an empty image if there is no `base`
+fn render_over<R>(
+    base: Option<&RenderedImage>,
+    width: u32,
+    height: u32,
+    draw: impl for<'a> FnOnce(&DrawingArea<Backend<'a>, Shift>) -> Result<R>,
+) -> Result<(RenderedImage, R)> {
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidSize { width, height });
+    }
+
+    trace!("width, height = {}, {}", width, height);
 
     // Initialize a backend depending on the features
     #[cfg(not(feature = "svg"))]
-    let mut pixel_buffer = slint::SharedPixelBuffer::new(width, height);
+    let mut pixel_buffer = match base {
+        Some(RenderedImage::Pixels(pixel_buffer)) => pixel_buffer.clone(),
+        _ => slint::SharedPixelBuffer::<slint::Rgb8Pixel>::new(width, height),
+    };
     #[cfg(not(feature = "svg"))]
     let backend = BitMapBackend::with_buffer(pixel_buffer.make_mut_bytes(), (width, height));
 
-    // Plotters requires TrueType fonts from the file system to draw axis text - we skip that for
-    // WASM for now
+    // Plotters requires TrueType fonts from the file system to draw axis text, which WASM does not
+    // have - draw it with an embedded font instead
     #[cfg(all(target_arch = "wasm32", not(feature = "svg")))]
-    let backend = wasm_backend::BackendWithoutText { backend };
+    let backend = wasm_backend::BackendWithEmbeddedFont { backend };
 
     #[cfg(feature = "svg")]
     let mut svg_string_buffer = String::new();
@@ -58,62 +509,83 @@ pub fn render_plot(data: &[f32], app: &MainWindow) -> Image {
     let backend = plotters_svg::SVGBackend::with_string(&mut svg_string_buffer, (width, height));
 
     let root = backend.into_drawing_area();
-    root.fill(&WHITE).expect("error filling drawing area");
-
-    // Build a 2D chart
-    let mut chart = ChartBuilder::on(&root)
-        .x_label_area_size(28)
-        .y_label_area_size(28)
-        .margin(20)
-        .build_cartesian_2d(
-            (x_min as f64)..(x_max as f64),
-            (y_min as f64)..(y_max as f64),
-        )
-        .expect("failed to build chart");
-
-    // Configure the chart
-    chart
-        .configure_mesh()
-        //.disable_mesh() // Disable mesh for faster SVG rendering
-        .bold_line_style(BLUE.mix(0.1))
-        .light_line_style(BLUE.mix(0.05))
-        .axis_style(ShapeStyle::from(BLUE.mix(0.45)).stroke_width(1))
-        .x_labels(20)
-        .x_label_style(("sans-serif", 15).into_font().color(&BLUE.mix(0.65)))
-        .x_label_formatter(&|x| format!("{}", x))
-        .y_labels(10)
-        .y_label_style(("sans-serif", 15).into_font().color(&BLUE.mix(0.65)))
-        .y_label_formatter(&|y| format!("{}", y))
-        .draw()
-        .expect("failed to draw chart mesh");
-
-    // Define the actual data series to be plotted
-    let area_series = AreaSeries::new(
-        data.iter().enumerate().map(|(x, y)| (x as f64, *y as f64)),
-        -1.0,
-        PLOT_LINE_COLOR.mix(0.175),
-    )
-    // Setting to 2 can cause plotters to hang see https://github.com/plotters-rs/plotters/issues/562
-    .border_style(ShapeStyle::from(PLOT_LINE_COLOR).stroke_width(1));
 
-    // Draw the defined data series onto the chart
-    chart
-        .draw_series(area_series)
-        .expect("failed to draw chart data");
+    let result = draw(&root)?;
 
     // Call explicitly to avoid errors being ignored on dropping
-    root.present().expect("error presenting");
-    drop(chart);
+    root.present()?;
     drop(root);
 
     debug!("Done Rendering. Sending to GUI.");
 
     #[cfg(not(feature = "svg"))]
     {
-        Image::from_rgb8(pixel_buffer)
+        Ok((RenderedImage::Pixels(pixel_buffer), result))
     }
     #[cfg(feature = "svg")]
     {
-        Image::load_from_svg_data(svg_string_buffer.as_bytes()).unwrap()
+        let svg = match base {
+            Some(RenderedImage::Svg(base)) => splice_svg(base, &svg_string_buffer),
+            _ => svg_string_buffer,
+        };
+        Ok((RenderedImage::Svg(svg), result))
+    }
+}
+
+/// Insert the elements of the SVG document `overlay` before the end of the document `base`, so
+/// they are drawn on top of it. Plotters has no way to continue drawing onto a finished document.
+#[cfg(feature = "svg")]
+fn splice_svg(base: &str, overlay: &str) -> String {
+    let base_end = base.rfind("</svg>").unwrap_or(base.len());
+    // Skip anything before the root element, e.g., an XML declaration, and its start tag
+    let elements_start = overlay
+        .find("<svg")
+        .and_then(|start| overlay[start..].find('>').map(|end| start + end + 1))
+        .unwrap_or(0);
+    format!("{}{}", &base[..base_end], &overlay[elements_start..])
+}
+
+#[cfg(all(test, feature = "svg"))]
+mod tests {
+    use super::*;
+
+    /// The overlay must end up inside the single root element of the base, otherwise the cached
+    /// background is lost or the document cannot be loaded
+    #[test]
+    fn draw_over_svg() {
+        let (base, ()) = render_to_image(64, 32, WHITE, |root| {
+            root.draw(&Rectangle::new([(0, 0), (10, 10)], BLUE.filled()))?;
+            Ok(())
+        })
+        .unwrap();
+        let (image, ()) = render_over(Some(&base), 64, 32, |root| {
+            root.draw(&Circle::new((32, 16), 5, RED.filled()))?;
+            Ok(())
+        })
+        .unwrap();
+
+        let RenderedImage::Svg(svg) = &image else {
+            panic!("expected an SVG");
+        };
+        assert_eq!(svg.matches("<svg").count(), 1);
+        assert_eq!(svg.matches("</svg>").count(), 1);
+        assert!(svg.trim_end().ends_with("</svg>"));
+        let (rect, circle) = (svg.find("<rect").unwrap(), svg.find("<circle").unwrap());
+        assert!(rect < circle, "overlay must be drawn last");
+
+        let size = image.into_image().unwrap().size();
+        assert_eq!((size.width, size.height), (64, 32));
+    }
+
+    #[test]
+    fn splice_svg_with_declaration() {
+        let document = |element: &str| {
+            format!(
+                "<?xml version=\"1.0\"?>\n<svg width=\"8\" height=\"8\" \
+                 xmlns=\"http://www.w3.org/2000/svg\">\n{element}\n</svg>\n"
+            )
+        };
+        let svg = splice_svg(&document("<rect/>"), &document("<circle/>"));
+        assert_eq!(svg, document("<rect/>\n\n<circle/>"));
     }
 }
@@ -0,0 +1,114 @@
+//! Native plot drawn by Slint itself: the traces are handed over as [`Path`] commands and the axes
+//! are made of Slint elements, so neither plotters nor SVG parsing is involved.
+//!
+//! [`Path`]: https://slint.dev/docs/slint/src/language/builtins/elements#path
+
+use super::{nice_step, theme::to_slint_color, SpectrumPlot};
+use crate::{AxisTick, ChartArea, MainWindow, NativeTrace, PlotMeta};
+use slint::{ComponentHandle, ModelRc, VecModel};
+use std::fmt::Write;
+
+/// Size of the viewbox of the paths in `plotter.slint`
+const VIEWBOX: f32 = 1000.0;
+
+/// Space around the plotting area for the labels in pixels: left, top, right, bottom
+const MARGINS: [f32; 4] = [60.0, 20.0, 20.0, 35.0];
+
+/// Show `plot` with the native Slint elements of the GUI
+pub(super) fn show(plot: &SpectrumPlot, app: &MainWindow) {
+    let meta = app.global::<PlotMeta>();
+    let width = app.get_plot_width().max(1.0);
+    let height = app.get_plot_height().max(1.0);
+
+    let [left, top, right, bottom] = MARGINS;
+    meta.set_chart(ChartArea {
+        left: left / width,
+        top: top / height,
+        right: 1.0 - right / width,
+        bottom: 1.0 - bottom / height,
+        x_min: plot.x_min,
+        x_max: plot.x_max,
+        y_min: plot.y_min,
+        y_max: plot.y_max,
+    });
+
+    // The live spectrum is filled in the trace color of the theme, other channels are lines
+    let live = plot.traces.iter().find(|trace| trace.filled);
+    let trace = live
+        .map(|live| path_commands(plot, &live.data))
+        .unwrap_or_default();
+    // Close the area below the trace along the bottom of the plotting area
+    let fill = match live {
+        Some(live) if !trace.is_empty() => {
+            let first_x = to_viewbox_x(plot, plot.first_bin as f32);
+            let last_x = to_viewbox_x(plot, (plot.first_bin + live.data.len() - 1) as f32);
+            format!("{trace} L {last_x} {VIEWBOX} L {first_x} {VIEWBOX} Z")
+        }
+        _ => String::new(),
+    };
+    let channels: Vec<NativeTrace> = plot
+        .traces
+        .iter()
+        .filter(|trace| !trace.filled)
+        .map(|trace| NativeTrace {
+            commands: path_commands(plot, &trace.data).into(),
+            color: to_slint_color(trace.color),
+        })
+        .collect();
+    let reference = plot
+        .reference
+        .as_ref()
+        .map(|reference| path_commands(plot, reference))
+        .unwrap_or_default();
+
+    meta.set_native_trace(trace.into());
+    meta.set_native_fill(fill.into());
+    meta.set_native_reference(reference.into());
+    meta.set_native_channels(ModelRc::new(VecModel::from(channels)));
+
+    // Roughly as many ticks as the plotters chart has labels
+    meta.set_x_ticks(ticks(plot.x_min, plot.x_max, 20));
+    meta.set_y_ticks(ticks(plot.y_min, plot.y_max, 10));
+}
+
+/// `M x y L x y ...` for all visible bins of `trace`
+fn path_commands(plot: &SpectrumPlot, trace: &[f32]) -> String {
+    let mut commands = String::new();
+    for (bin, level) in trace.iter().enumerate() {
+        if !level.is_finite() {
+            continue;
+        }
+        let x = to_viewbox_x(plot, (plot.first_bin + bin) as f32);
+        // Keep far-off values close to the visible area, they are clipped anyway
+        let y = ((plot.y_max - level) / (plot.y_max - plot.y_min) * VIEWBOX)
+            .clamp(-VIEWBOX, 2.0 * VIEWBOX);
+        let command = if commands.is_empty() { 'M' } else { 'L' };
+        let _ = write!(commands, "{command} {x:.2} {y:.2} ");
+    }
+    commands.trim_end().to_string()
+}
+
+fn to_viewbox_x(plot: &SpectrumPlot, x: f32) -> f32 {
+    (x - plot.x_min) / (plot.x_max - plot.x_min) * VIEWBOX
+}
+
+/// About `count` ticks at "nice" values (multiples of 1, 2 or 5 times a power of ten)
+fn ticks(min: f32, max: f32, count: usize) -> ModelRc<AxisTick> {
+    let span = max - min;
+    let Some(step) = nice_step(span, count) else {
+        return ModelRc::default();
+    };
+
+    // Enough decimals to tell the ticks apart, but no rounding artifacts
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+    let ticks: Vec<AxisTick> = (0..)
+        .map(|i| (min / step).ceil() * step + i as f32 * step)
+        .take_while(|value| *value <= max)
+        .map(|value| AxisTick {
+            position: (value - min) / span,
+            label: format!("{value:.decimals$}").into(),
+        })
+        .collect();
+    ModelRc::new(VecModel::from(ticks))
+}
@@ -0,0 +1,139 @@
+use super::{
+    channels,
+    config::{PlotConfig, YRange},
+    SpectrumPlot,
+};
+use crate::{
+    error::{Error, Result},
+    ChartArea, MainWindow, PlotMeta,
+};
+use log::debug;
+use plotters::prelude::*;
+use slint::ComponentHandle;
+use std::path::Path;
+
+/// File formats a snapshot of the plot can be exported to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Png,
+    Svg,
+}
+
+impl SnapshotFormat {
+    /// Derive the format from the extension of `file_name`, e.g., `snapshot.svg`
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = Path::new(file_name).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Render the most recently plotted spectra of all channels with the current GUI settings at an
+/// arbitrary resolution and encode it as `format`.
+///
+/// This is independent of the `svg` feature, so both formats are always available.
+/// Returns `None` if nothing has been plotted yet.
+pub fn render_snapshot(
+    app: &MainWindow,
+    format: SnapshotFormat,
+    width: u32,
+    height: u32,
+) -> Result<Option<Vec<u8>>> {
+    let frames = channels::last_frames();
+    if frames.first().is_none_or(Vec::is_empty) {
+        return Ok(None);
+    }
+    let frames: Vec<&[f32]> = frames.iter().map(Vec::as_slice).collect();
+
+    debug!("Rendering {width}x{height} {format:?} snapshot");
+    let mut config = PlotConfig {
+        width,
+        height,
+        ..PlotConfig::from_app(app)
+    };
+    // Keep the (smoothed) auto-range that is currently shown
+    let chart = app.global::<PlotMeta>().get_chart();
+    if config.y_range == YRange::Fit && chart.y_max > chart.y_min {
+        config.y_range = YRange::Fixed(chart.y_min, chart.y_max);
+    }
+    Ok(Some(
+        super::render_channels(&frames, &config, format)?.image,
+    ))
+}
+
+/// Render a snapshot like [`render_snapshot`] in the format given by the extension of `file_name`
+/// and save it with [`crate::export::save_file`]. Returns `false` if nothing has been plotted yet.
+pub fn save_snapshot(app: &MainWindow, file_name: &str, width: u32, height: u32) -> Result<bool> {
+    let format = SnapshotFormat::from_file_name(file_name)
+        .ok_or_else(|| Error::Image(format!("{file_name} must end with .png or .svg")))?;
+    let Some(bytes) = render_snapshot(app, format, width, height)? else {
+        return Ok(false);
+    };
+    crate::export::save_file(file_name, &bytes, format.mime_type())?;
+    Ok(true)
+}
+
+/// Draw `plot` at `width` x `height` pixels and encode it as `format`. Also returns where the
+/// chart was placed.
+pub(super) fn encode(
+    plot: &SpectrumPlot,
+    format: SnapshotFormat,
+    width: u32,
+    height: u32,
+) -> Result<(Vec<u8>, ChartArea)> {
+    if width == 0 || height == 0 {
+        return Err(Error::InvalidSize { width, height });
+    }
+    match format {
+        SnapshotFormat::Svg => {
+            let mut svg = String::new();
+            let chart = {
+                let root = plotters_svg::SVGBackend::with_string(&mut svg, (width, height))
+                    .into_drawing_area();
+                root.fill(&plot.theme.background)?;
+                let chart = plot.draw(&root)?;
+                root.present()?;
+                chart
+            };
+            Ok((svg.into_bytes(), chart))
+        }
+        SnapshotFormat::Png => {
+            let mut pixels = vec![0u8; width as usize * height as usize * 3];
+            let chart = {
+                let backend = BitMapBackend::with_buffer(&mut pixels, (width, height));
+                #[cfg(target_arch = "wasm32")]
+                let backend = super::wasm_backend::BackendWithEmbeddedFont { backend };
+
+                let root = backend.into_drawing_area();
+                root.fill(&plot.theme.background)?;
+                let chart = plot.draw(&root)?;
+                root.present()?;
+                chart
+            };
+            Ok((encode_png(&pixels, width, height)?, chart))
+        }
+    }
+}
+
+/// Encode an RGB8 pixel buffer as PNG
+fn encode_png(pixels: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| Error::Image(format!("failed to encode PNG: {e}")))?;
+    Ok(png)
+}
@@ -0,0 +1,219 @@
+use crate::{MainWindow, Theme};
+use plotters::prelude::*;
+use plotters::style::Color as _;
+use slint::{Color, ComponentHandle};
+
+/// Colors and fonts of the plots and the surrounding GUI
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlotTheme {
+    /// Background of the plot image
+    pub background: RGBColor,
+    /// Base color of mesh, axes and labels
+    pub foreground: RGBColor,
+    /// Live trace and constellation points
+    pub trace: RGBColor,
+    /// Reference trace
+    pub reference: RGBColor,
+    /// Opacity of the area below the live trace
+    pub fill_opacity: f64,
+    /// Scales the opacity of mesh, axes and labels, `1.0` being rather subtle
+    pub contrast: f64,
+    pub font_family: &'static str,
+    pub font_size: u32,
+    /// Background of the window
+    pub window: RGBColor,
+    /// Background of the frame around the plots
+    pub panel: RGBColor,
+    /// Text of the GUI
+    pub text: RGBColor,
+    /// Crosshair and border of the tooltip that read out the plot
+    pub cursor: RGBColor,
+    /// Background of the error banner
+    pub error: RGBColor,
+}
+
+impl Default for PlotTheme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl PlotTheme {
+    /// Colors of the channels after the first one, which uses the trace color. Picked to stand
+    /// out from the trace and reference colors of all presets.
+    pub const CHANNEL_COLORS: [RGBColor; 6] = [
+        RGBColor(214, 39, 40),
+        RGBColor(44, 160, 44),
+        RGBColor(148, 103, 189),
+        RGBColor(227, 119, 194),
+        RGBColor(140, 86, 75),
+        RGBColor(188, 189, 34),
+    ];
+
+    /// Names of the presets in the order of [`PlotTheme::preset`]
+    pub const PRESETS: [&'static str; 4] = ["Light", "Dark", "High Contrast", "Custom"];
+
+    pub fn light() -> Self {
+        Self {
+            background: WHITE,
+            foreground: BLUE,
+            trace: RGBColor(0, 175, 255),
+            reference: RGBColor(255, 140, 0),
+            fill_opacity: 0.175,
+            contrast: 1.0,
+            font_family: "sans-serif",
+            font_size: 15,
+            window: RGBColor(240, 240, 240),
+            panel: RGBColor(211, 211, 211),
+            text: BLACK,
+            cursor: RED,
+            error: RGBColor(183, 28, 28),
+        }
+    }
+
+    /// Matches the page background of `index.html`
+    pub fn dark() -> Self {
+        Self {
+            background: RGBColor(24, 40, 65),
+            foreground: RGBColor(200, 215, 255),
+            trace: RGBColor(0, 175, 255),
+            reference: RGBColor(255, 165, 0),
+            fill_opacity: 0.25,
+            contrast: 1.5,
+            window: RGBColor(16, 27, 44),
+            panel: RGBColor(36, 52, 80),
+            text: RGBColor(230, 235, 245),
+            cursor: RGBColor(255, 99, 71),
+            ..Self::light()
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            background: BLACK,
+            foreground: WHITE,
+            trace: YELLOW,
+            reference: CYAN,
+            fill_opacity: 0.3,
+            contrast: 3.0,
+            font_size: 18,
+            window: BLACK,
+            panel: RGBColor(40, 40, 40),
+            text: WHITE,
+            cursor: MAGENTA,
+            ..Self::light()
+        }
+    }
+
+    /// Custom colors for background, foreground (mesh, axes, labels and text) and trace
+    pub fn custom(background: RGBColor, foreground: RGBColor, trace: RGBColor) -> Self {
+        Self {
+            background,
+            foreground,
+            trace,
+            window: background,
+            panel: background,
+            text: foreground,
+            ..Self::light()
+        }
+    }
+
+    /// Preset by its index in [`PlotTheme::PRESETS`], custom colors are handled by
+    /// [`PlotTheme::from_app`]
+    pub fn preset(index: i32) -> Self {
+        match index {
+            1 => Self::dark(),
+            2 => Self::high_contrast(),
+            _ => Self::light(),
+        }
+    }
+
+    /// Theme currently selected in the GUI.
+    ///
+    /// Custom colors that cannot be parsed fall back to the light theme.
+    pub fn from_app(app: &MainWindow) -> Self {
+        let theme = app.global::<Theme>();
+        if theme.get_preset() as usize != Self::PRESETS.len() - 1 {
+            return Self::preset(theme.get_preset());
+        }
+
+        let light = Self::light();
+        Self::custom(
+            parse_hex_color(&theme.get_custom_background()).unwrap_or(light.background),
+            parse_hex_color(&theme.get_custom_foreground()).unwrap_or(light.foreground),
+            parse_hex_color(&theme.get_custom_trace()).unwrap_or(light.trace),
+        )
+    }
+
+    /// Apply the colors to the widgets of the GUI
+    pub fn apply_to_ui(&self, app: &MainWindow) {
+        let theme = app.global::<Theme>();
+        theme.set_window_background(to_slint_color(self.window));
+        theme.set_panel_background(to_slint_color(self.panel));
+        theme.set_text(to_slint_color(self.text));
+
+        theme.set_plot_background(to_slint_color(self.background));
+        theme.set_grid(to_slint_color_with_alpha(self.bold_line_style()));
+        theme.set_label(to_slint_color_with_alpha(
+            self.foreground.mix((0.65 * self.contrast).min(1.0)),
+        ));
+        theme.set_trace(to_slint_color(self.trace));
+        theme.set_trace_fill(to_slint_color_with_alpha(self.trace.mix(self.fill_opacity)));
+        theme.set_reference(to_slint_color(self.reference));
+
+        theme.set_crosshair(to_slint_color_with_alpha(
+            self.cursor.mix((0.5 * self.contrast).min(1.0)),
+        ));
+        theme.set_tooltip_background(to_slint_color_with_alpha(self.background.mix(0.88)));
+        theme.set_tooltip_text(to_slint_color(self.text));
+        theme.set_selection(to_slint_color(self.foreground));
+        theme.set_selection_fill(to_slint_color_with_alpha(self.foreground.mix(0.125)));
+        theme.set_error_background(to_slint_color(self.error));
+        theme.set_error_text(to_slint_color(WHITE));
+        super::channels::recolor(app, self);
+    }
+
+    /// Color of the channel at `index`, repeating the colors if there are many channels
+    pub fn channel_color(&self, index: usize) -> RGBColor {
+        match index {
+            0 => self.trace,
+            _ => Self::CHANNEL_COLORS[(index - 1) % Self::CHANNEL_COLORS.len()],
+        }
+    }
+
+    pub fn bold_line_style(&self) -> RGBAColor {
+        self.foreground.mix((0.1 * self.contrast).min(1.0))
+    }
+
+    pub fn light_line_style(&self) -> RGBAColor {
+        self.foreground.mix((0.05 * self.contrast).min(1.0))
+    }
+
+    pub fn axis_style(&self) -> ShapeStyle {
+        ShapeStyle::from(self.foreground.mix((0.45 * self.contrast).min(1.0))).stroke_width(1)
+    }
+
+    pub fn label_style(&self) -> TextStyle<'static> {
+        (self.font_family, self.font_size)
+            .into_font()
+            .color(&self.foreground.mix((0.65 * self.contrast).min(1.0)))
+    }
+}
+
+/// Parse colors like `#00afff` or `00afff`
+pub fn parse_hex_color(hex: &str) -> Option<RGBColor> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+pub(super) fn to_slint_color(RGBColor(r, g, b): RGBColor) -> Color {
+    Color::from_rgb_u8(r, g, b)
+}
+
+fn to_slint_color_with_alpha(RGBAColor(r, g, b, a): RGBAColor) -> Color {
+    Color::from_argb_u8((a * 255.0).round() as u8, r, g, b)
+}
@@ -0,0 +1,178 @@
+//! Zoom, pan and read out values of the spectrum plot with the mouse.
+//!
+//! Mouse positions are given as fractions of the plot image size (`0.0..=1.0`), so they are
+//! independent of the resolution the plot is rendered at. Zooming and panning change the `zoom-*`
+//! properties directly, so consecutive mouse events add up even before the next frame is plotted.
+
+use super::channels;
+use crate::{error, ChartArea, HoverInfo, MainWindow, PlotMeta};
+use slint::{ComponentHandle, Model};
+
+/// Zoom factor per step of the mouse wheel
+const WHEEL_ZOOM_FACTOR: f32 = 1.25;
+
+/// Smallest visible x-range in bins, so plotters always has a proper range to work with
+const MIN_X_SPAN: f32 = 2.0;
+
+impl ChartArea {
+    /// Data x coordinate at horizontal image position `fx`
+    pub fn data_x(&self, fx: f32) -> f32 {
+        self.x_min + (fx - self.left) / (self.right - self.left) * (self.x_max - self.x_min)
+    }
+
+    /// Data y coordinate at vertical image position `fy`
+    pub fn data_y(&self, fy: f32) -> f32 {
+        self.y_max - (fy - self.top) / (self.bottom - self.top) * (self.y_max - self.y_min)
+    }
+
+    /// Horizontal image position of data x coordinate `x`
+    pub fn image_x(&self, x: f32) -> f32 {
+        self.left + (x - self.x_min) / (self.x_max - self.x_min) * (self.right - self.left)
+    }
+
+    /// Vertical image position of data y coordinate `y`
+    pub fn image_y(&self, y: f32) -> f32 {
+        self.top + (self.y_max - y) / (self.y_max - self.y_min) * (self.bottom - self.top)
+    }
+
+    /// Whether the image position lies within the plotting area
+    pub fn contains(&self, fx: f32, fy: f32) -> bool {
+        (self.left..=self.right).contains(&fx) && (self.top..=self.bottom).contains(&fy)
+    }
+}
+
+/// Crosshair position and readout of the bin closest to the mouse position `(fx, fy)`
+pub fn hover_info(app: &MainWindow, (fx, fy): (f32, f32)) -> HoverInfo {
+    let meta = app.global::<PlotMeta>();
+    let chart = meta.get_chart();
+    let frame = meta.get_last_frame();
+    if frame.row_count() == 0 || !chart.contains(fx, fy) {
+        return HoverInfo::default();
+    }
+
+    // Snap to the nearest bin that is actually visible
+    let bin = chart
+        .data_x(fx)
+        .round()
+        .clamp(chart.x_min.ceil(), chart.x_max.floor())
+        .clamp(0.0, (frame.row_count() - 1) as f32) as usize;
+    let Some(mut level) = frame.row_data(bin) else {
+        return HoverInfo::default();
+    };
+
+    // Read out what is actually plotted
+    let reference = meta.get_reference();
+    if meta.get_reference_difference() && reference.row_count() == frame.row_count() {
+        level -= reference.row_data(bin).unwrap_or_default();
+    }
+    // The live spectrum is drawn shifted by the offset of its channel, the level is read out as is
+    let offset = channels::styles(app)
+        .first()
+        .map_or(0.0, |style| style.offset);
+
+    let sample_rate = meta.get_sample_rate();
+    let position = if sample_rate > 0.0 {
+        let frequency = bin as f32 * sample_rate / frame.row_count() as f32;
+        format!("Bin {bin}: {frequency:.1} Hz")
+    } else {
+        format!("Bin {bin}")
+    };
+
+    HoverInfo {
+        valid: true,
+        x: chart.image_x(bin as f32),
+        y: chart
+            .image_y(level + offset)
+            .clamp(chart.top.min(chart.bottom), chart.bottom.max(chart.top)),
+        label: format!("{position}\nLevel: {level:.2}").into(),
+    }
+}
+
+/// Zoom into the rectangle spanned by two corners, e.g., from a rubber band selection
+pub fn zoom_to_box(app: &MainWindow, (fx0, fy0): (f32, f32), (fx1, fy1): (f32, f32)) {
+    let chart = zoomed_chart(app);
+    let (x0, x1) = (chart.data_x(fx0), chart.data_x(fx1));
+    let (y0, y1) = (chart.data_y(fy0), chart.data_y(fy1));
+    set_zoom(app, x0.min(x1), x0.max(x1), y0.min(y1), y0.max(y1));
+}
+
+/// Zoom in (`steps > 0`) or out (`steps < 0`) around the data point under the cursor
+pub fn zoom_around(app: &MainWindow, (fx, fy): (f32, f32), steps: f32) {
+    let chart = zoomed_chart(app);
+    let (x, y) = (chart.data_x(fx), chart.data_y(fy));
+    let scale = WHEEL_ZOOM_FACTOR.powf(-steps);
+    set_zoom(
+        app,
+        x - (x - chart.x_min) * scale,
+        x + (chart.x_max - x) * scale,
+        y - (y - chart.y_min) * scale,
+        y + (chart.y_max - y) * scale,
+    );
+}
+
+/// Move the visible range by the given distance of the mouse on the image
+pub fn pan(app: &MainWindow, (dfx, dfy): (f32, f32)) {
+    let chart = zoomed_chart(app);
+    let mut dx = dfx / (chart.right - chart.left) * (chart.x_max - chart.x_min);
+    let dy = dfy / (chart.bottom - chart.top) * (chart.y_max - chart.y_min);
+
+    // Stop at the first and last bin instead of moving the range out of the spectrum
+    let last_bin = channels::bins().saturating_sub(1) as f32;
+    if chart.x_max - chart.x_min <= last_bin {
+        dx = dx.clamp(chart.x_max - last_bin, chart.x_min);
+    }
+    set_zoom(
+        app,
+        chart.x_min - dx,
+        chart.x_max - dx,
+        chart.y_min + dy,
+        chart.y_max + dy,
+    );
+}
+
+/// Show the full range again
+pub fn reset_zoom(app: &MainWindow) {
+    app.set_zoomed(false);
+    redraw(app);
+}
+
+/// Chart of the plot with the ranges it shows once the current zoom is plotted
+fn zoomed_chart(app: &MainWindow) -> ChartArea {
+    let chart = app.global::<PlotMeta>().get_chart();
+    if !app.get_zoomed() {
+        return chart;
+    }
+    ChartArea {
+        x_min: app.get_zoom_x_min(),
+        x_max: app.get_zoom_x_max(),
+        y_min: app.get_zoom_y_min(),
+        y_max: app.get_zoom_y_max(),
+        ..chart
+    }
+}
+
+/// Show a changed zoom right away if no new frames are plotted, i.e., while the plot is disabled
+fn redraw(app: &MainWindow) {
+    if app.get_plot_enable() {
+        return;
+    }
+    if let Err(e) = super::redraw(app) {
+        error::report(app, &e);
+    }
+}
+
+fn set_zoom(app: &MainWindow, x_min: f32, x_max: f32, y_min: f32, y_max: f32) {
+    // Ignore degenerate ranges, e.g., from a single click or before anything was plotted
+    if !(x_min.is_finite() && x_max.is_finite() && y_min.is_finite() && y_max.is_finite())
+        || x_max - x_min < MIN_X_SPAN
+        || y_max <= y_min
+    {
+        return;
+    }
+    app.set_zoom_x_min(x_min);
+    app.set_zoom_x_max(x_max);
+    app.set_zoom_y_min(y_min);
+    app.set_zoom_y_max(y_max);
+    app.set_zoomed(true);
+    redraw(app);
+}
@@ -1,15 +1,35 @@
 // Copyright © SixtyFPS GmbH <info@slint.dev>
 // SPDX-License-Identifier: MIT
 
-// File copied from here https://github.com/slint-ui/slint/blob/dab9a4fc2e3c9d84ee17d01b18e1adf6b0b6428b/examples/plotter/wasm_backend.rs
+// Based on https://github.com/slint-ui/slint/blob/dab9a4fc2e3c9d84ee17d01b18e1adf6b0b6428b/examples/plotter/wasm_backend.rs
+// Instead of dropping all text, it is rasterized with a font embedded into the binary, as there is
+// no file system to load system fonts from in the browser.
 
+use std::sync::OnceLock;
+
+use plotters_backend::text_anchor::{HPos, VPos};
 use plotters_backend::*;
 
-pub struct BackendWithoutText<ForwardedBackend: DrawingBackend> {
+/// DejaVu Sans Mono, see `fonts/LICENSE-DejaVu.txt`.
+static FONT_DATA: &[u8] = include_bytes!("../../fonts/DejaVuSansMono.ttf");
+
+fn font() -> &'static fontdue::Font {
+    static FONT: OnceLock<fontdue::Font> = OnceLock::new();
+    FONT.get_or_init(|| {
+        fontdue::Font::from_bytes(FONT_DATA, fontdue::FontSettings::default())
+            .expect("embedded font is invalid")
+    })
+}
+
+/// Forwards everything to the wrapped backend, except text, which is drawn pixel by pixel from
+/// the embedded font. The font family and style requested by plotters are ignored.
+pub struct BackendWithEmbeddedFont<ForwardedBackend: DrawingBackend> {
     pub backend: ForwardedBackend,
 }
 
-impl<ForwardedBackend: DrawingBackend> DrawingBackend for BackendWithoutText<ForwardedBackend> {
+impl<ForwardedBackend: DrawingBackend> DrawingBackend
+    for BackendWithEmbeddedFont<ForwardedBackend>
+{
     type ErrorType = ForwardedBackend::ErrorType;
 
     fn get_size(&self) -> (u32, u32) {
@@ -80,19 +100,71 @@ impl<ForwardedBackend: DrawingBackend> DrawingBackend for BackendWithoutText<For
 
     fn draw_text<TStyle: BackendTextStyle>(
         &mut self,
-        _text: &str,
-        _style: &TStyle,
-        _pos: BackendCoord,
+        text: &str,
+        style: &TStyle,
+        pos: BackendCoord,
     ) -> Result<(), DrawingErrorKind<Self::ErrorType>> {
+        let font = font();
+        let size = style.size() as f32;
+        let color = style.color();
+        let transform = style.transform();
+        let (width, height) = self.estimate_text_size(text, style)?;
+
+        // Offset of the text's top left corner from the anchor, before rotation
+        let anchor = style.anchor();
+        let dx = match anchor.h_pos {
+            HPos::Left => 0,
+            HPos::Center => -(width as i32) / 2,
+            HPos::Right => -(width as i32),
+        };
+        let dy = match anchor.v_pos {
+            VPos::Top => 0,
+            VPos::Center => -(height as i32) / 2,
+            VPos::Bottom => -(height as i32),
+        };
+        let baseline = dy as f32 + ascent(font, size);
+
+        let mut pen_x = dx as f32;
+        for c in text.chars() {
+            let (metrics, coverage) = font.rasterize(c, size);
+            let left = pen_x.round() as i32 + metrics.xmin;
+            let top = baseline.round() as i32 - metrics.ymin - metrics.height as i32;
+
+            for (i, &value) in coverage.iter().enumerate() {
+                if value == 0 {
+                    continue;
+                }
+                let x = left + (i % metrics.width) as i32;
+                let y = top + (i / metrics.width) as i32;
+                let (x, y) = transform.transform(x, y);
+                let pixel_color = BackendColor {
+                    alpha: color.alpha * value as f64 / 255.0,
+                    rgb: color.rgb,
+                };
+                self.backend
+                    .draw_pixel((pos.0 + x, pos.1 + y), pixel_color)?;
+            }
+            pen_x += metrics.advance_width;
+        }
         Ok(())
     }
 
     fn estimate_text_size<TStyle: BackendTextStyle>(
         &self,
-        _text: &str,
-        _style: &TStyle,
+        text: &str,
+        style: &TStyle,
     ) -> Result<(u32, u32), DrawingErrorKind<Self::ErrorType>> {
-        Ok((0, 0))
+        let font = font();
+        let size = style.size() as f32;
+        let width: f32 = text
+            .chars()
+            .map(|c| font.metrics(c, size).advance_width)
+            .sum();
+        let height = font
+            .horizontal_line_metrics(size)
+            .map(|m| m.ascent - m.descent)
+            .unwrap_or(size);
+        Ok((width.ceil() as u32, height.ceil() as u32))
     }
 
     fn blit_bitmap<'b>(
@@ -104,3 +176,10 @@ impl<ForwardedBackend: DrawingBackend> DrawingBackend for BackendWithoutText<For
         self.backend.blit_bitmap(pos, (iw, ih), src)
     }
 }
+
+/// Distance from the top of a line of text to its baseline.
+fn ascent(font: &fontdue::Font, size: f32) -> f32 {
+    font.horizontal_line_metrics(size)
+        .map(|m| m.ascent)
+        .unwrap_or(size)
+}
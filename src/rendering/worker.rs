@@ -0,0 +1,234 @@
+//! The GUI thread only gathers what to draw as [`RenderJob`]. Drawing happens on a worker thread
+//! (or deferred on the event loop on WASM, where there are no threads) and only the finished
+//! image is handed back to the event loop.
+//!
+//! There is at most one pending job and one finished frame per [`Target`]. A newer job or frame
+//! replaces an older one that has not been processed yet, so slow rendering never piles up.
+//!
+//! The static background of every target (mesh, axes, labels) is cached and only redrawn when its
+//! [`BackgroundKey`] changes. Otherwise, only the data is drawn onto a copy of the cached image.
+
+use super::{render_over, render_to_image, theme::PlotTheme, Backend, RenderedImage};
+use crate::{
+    error::{self, Result},
+    ChartArea, MainWindow, PlotMeta,
+};
+use log::{trace, warn};
+use plotters::{coord::Shift, prelude::*};
+use slint::{ComponentHandle, Weak};
+use std::{
+    cell::OnceCell,
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// Image of the GUI a job is rendered for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    Spectrum = 0,
+    Constellation = 1,
+}
+
+const TARGETS: [Target; 2] = [Target::Spectrum, Target::Constellation];
+
+type DrawBackgroundFn =
+    Box<dyn for<'a> FnOnce(&DrawingArea<Backend<'a>, Shift>) -> Result<()> + Send>;
+type DrawFn =
+    Box<dyn for<'a> FnOnce(&DrawingArea<Backend<'a>, Shift>) -> Result<Option<ChartArea>> + Send>;
+
+/// Everything needed to render one image without access to the GUI
+pub(crate) struct RenderJob {
+    pub width: u32,
+    pub height: u32,
+    pub background: RGBColor,
+    /// Identifies the output of `draw_background`
+    pub background_key: BackgroundKey,
+    /// Draws everything that does not change from frame to frame onto the drawing area
+    pub draw_background: DrawBackgroundFn,
+    /// Draws the data onto the background and optionally returns where the chart was placed
+    pub draw: DrawFn,
+}
+
+/// Everything the static background of a plot depends on
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BackgroundKey {
+    pub width: u32,
+    pub height: u32,
+    /// `[x_min, x_max, y_min, y_max]`
+    pub ranges: [f32; 4],
+    pub theme: PlotTheme,
+}
+
+struct Frame {
+    image: RenderedImage,
+    chart_area: Option<ChartArea>,
+}
+
+#[derive(Default)]
+struct Shared {
+    jobs: Mutex<[Option<RenderJob>; TARGETS.len()]>,
+    job_ready: Condvar,
+    frames: Mutex<[Option<Frame>; TARGETS.len()]>,
+    /// Only used by the thread that renders
+    backgrounds: Mutex<[Option<(BackgroundKey, RenderedImage)>; TARGETS.len()]>,
+}
+
+struct Renderer {
+    shared: Arc<Shared>,
+    #[cfg(target_arch = "wasm32")]
+    window: Weak<MainWindow>,
+}
+
+thread_local! {
+    // Lives on the GUI thread, as all jobs are submitted from there
+    static RENDERER: OnceCell<Renderer> = const { OnceCell::new() };
+}
+
+/// Queue `job` for rendering, replacing a job for the same `target` that has not started yet.
+/// Errors while rendering are reported in the GUI.
+pub(crate) fn submit(app: &MainWindow, target: Target, job: RenderJob) -> Result<()> {
+    RENDERER.with(|renderer| {
+        let renderer = match renderer.get() {
+            Some(renderer) => renderer,
+            None => {
+                let new = Renderer::new(app.as_weak())?;
+                renderer.get_or_init(|| new)
+            }
+        };
+        renderer.submit(target, job)
+    })
+}
+
+impl Renderer {
+    fn new(window: Weak<MainWindow>) -> Result<Self> {
+        let shared = Arc::new(Shared::default());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let thread_shared = shared.clone();
+            std::thread::Builder::new()
+                .name("plot renderer".to_string())
+                .spawn(move || loop {
+                    let jobs = {
+                        let mut jobs = thread_shared.jobs.lock().unwrap();
+                        while jobs.iter().all(Option::is_none) {
+                            jobs = thread_shared.job_ready.wait(jobs).unwrap();
+                        }
+                        std::mem::take(&mut *jobs)
+                    };
+                    render_jobs(&thread_shared, &window, jobs);
+                })?;
+            Ok(Self { shared })
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        Ok(Self { shared, window })
+    }
+
+    fn submit(&self, target: Target, job: RenderJob) -> Result<()> {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        #[cfg(target_arch = "wasm32")]
+        let idle = jobs.iter().all(Option::is_none);
+        if jobs[target as usize].replace(job).is_some() {
+            trace!("Skipping outdated {target:?} job");
+        }
+        drop(jobs);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.shared.job_ready.notify_one();
+
+        // Render once the event loop had the chance to process pending input events
+        #[cfg(target_arch = "wasm32")]
+        if idle {
+            let shared = self.shared.clone();
+            let window = self.window.clone();
+            slint::invoke_from_event_loop(move || {
+                let jobs = std::mem::take(&mut *shared.jobs.lock().unwrap());
+                render_jobs(&shared, &window, jobs);
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn render_jobs(
+    shared: &Arc<Shared>,
+    window: &Weak<MainWindow>,
+    jobs: [Option<RenderJob>; TARGETS.len()],
+) {
+    let mut backgrounds = shared.backgrounds.lock().unwrap();
+    for (target, job) in TARGETS.into_iter().zip(jobs) {
+        let Some(job) = job else { continue };
+
+        let (image, chart_area) = match render_job(target, &mut backgrounds[target as usize], job) {
+            Ok(frame) => frame,
+            Err(e) => {
+                let message = e.to_string();
+                let reported = window.upgrade_in_event_loop(move |app| {
+                    app.set_error_message(message.into());
+                });
+                warn!("Failed to render {target:?} plot: {e}");
+                if reported.is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        let mut frames = shared.frames.lock().unwrap();
+        let idle = frames.iter().all(Option::is_none);
+        frames[target as usize] = Some(Frame { image, chart_area });
+
+        // A frame that is already waiting for the event loop will pick up the new one as well
+        if idle {
+            let shared = shared.clone();
+            let handed_over = window.upgrade_in_event_loop(move |app| {
+                let frames = std::mem::take(&mut *shared.frames.lock().unwrap());
+                for (target, frame) in TARGETS.into_iter().zip(frames) {
+                    if let Some(frame) = frame {
+                        if let Err(e) = show(&app, target, frame) {
+                            error::report(&app, &e);
+                        }
+                    }
+                }
+            });
+            // Without an event loop, there is nobody to show the plot to
+            if let Err(e) = handed_over {
+                trace!("Dropping rendered plot: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Draw the data of `job` onto the cached `background`, which is redrawn first if outdated
+fn render_job(
+    target: Target,
+    background: &mut Option<(BackgroundKey, RenderedImage)>,
+    job: RenderJob,
+) -> Result<(RenderedImage, Option<ChartArea>)> {
+    if background.as_ref().map(|(key, _)| key) != Some(&job.background_key) {
+        trace!("Redrawing {target:?} background");
+        // Never draw onto an outdated background if redrawing it failed
+        *background = None;
+        let (image, ()) =
+            render_to_image(job.width, job.height, job.background, job.draw_background)?;
+        *background = Some((job.background_key, image));
+    }
+    let base = background.as_ref().map(|(_, image)| image);
+    render_over(base, job.width, job.height, job.draw)
+}
+
+fn show(app: &MainWindow, target: Target, frame: Frame) -> Result<()> {
+    let image = frame.image.into_image()?;
+    match target {
+        Target::Spectrum => {
+            app.set_plot_frame(image);
+            // Remember where the chart ended up to map mouse positions to data coordinates
+            if let Some(chart_area) = frame.chart_area {
+                app.global::<PlotMeta>().set_chart(chart_area);
+            }
+        }
+        Target::Constellation => app.set_constellation_frame(image),
+    }
+    Ok(())
+}
@@ -1,29 +1,54 @@
-use crate::{vector, MainWindow};
+use crate::{
+    error::{self, Result},
+    supervisor::SourceHandle,
+    vector, MainWindow,
+};
 
-pub async fn wait_for_samples(window_weak: slint::Weak<MainWindow>) {
+/// Replay the recorded `vector::TEST_DATA` in a loop until `source` is cancelled
+pub async fn wait_for_samples(
+    window_weak: slint::Weak<MainWindow>,
+    source: SourceHandle,
+) -> Result<()> {
     use crate::rendering::render_plot;
     use std::time::Duration;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    source.set_running();
     loop {
         let window_weak = window_weak.clone();
 
         for values in vector::TEST_DATA.chunks_exact(vector::FFT_SIZE) {
+            if source.is_cancelled() {
+                return Ok(());
+            }
+
             let window_weak = window_weak.clone();
-            window_weak
-                .upgrade_in_event_loop(move |app| {
-                    if app.get_plot_enable() {
-                        app.set_plot_frame(render_plot(&values, &app))
+            window_weak.upgrade_in_event_loop(move |app| {
+                if app.get_plot_enable() {
+                    if let Err(e) = render_plot(values, &app) {
+                        error::report(&app, &e);
                     }
-                })
-                .unwrap();
+                }
+            })?;
 
             #[cfg(not(target_arch = "wasm32"))]
-            tokio::runtime::Runtime::new()
-                .unwrap()
-                .spawn(async { tokio::time::sleep(Duration::from_millis(100)).await })
-                .await
-                .unwrap();
+            let sleep = async {
+                runtime
+                    .spawn(async { tokio::time::sleep(Duration::from_millis(100)).await })
+                    .await
+                    .map_err(|e| error::Error::Source(format!("replay timer failed: {e}")))
+            };
             #[cfg(target_arch = "wasm32")]
-            gloo_timers::future::sleep(Duration::from_millis(200)).await; // Replay a little slower on the browser
+            let sleep = async {
+                // Replay a little slower on the browser
+                gloo_timers::future::sleep(Duration::from_millis(200)).await;
+                Ok(())
+            };
+            if let Some(Err(e)) = source.until_cancelled(sleep).await {
+                return Err(e);
+            }
         }
     }
 }
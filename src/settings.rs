@@ -0,0 +1,460 @@
+//! Persist the user's settings between sessions.
+//!
+//! Settings are stored as `key = value` lines with the value in JSON, e.g., `noise = 5` or
+//! `source_path = "capture.cf32"`: in a file in the platform config directory on desktop and in
+//! the browser's `localStorage` on WASM. Unknown keys are ignored and missing or invalid values
+//! keep their default, so settings can be added or removed without invalidating stored ones.
+//! The format is derived from [`Settings`] with serde, so to persist a new setting, add a field
+//! to it and handle it in [`Settings::from_app`] and [`Settings::apply`].
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use slint::ComponentHandle;
+
+use crate::{
+    error::{Error, Result},
+    rendering::channels,
+    MainWindow, PlotMeta, Theme,
+};
+
+/// Name used for the config directory on desktop and the `localStorage` key on WASM
+const STORAGE_NAME: &str = "slint-futuresdr-demo";
+
+/// Everything the user can configure in [`MainWindow`]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    pub y_axis_min: i32,
+    pub y_axis_max: i32,
+    pub y_auto_update: bool,
+    pub auto_range_headroom: i32,
+    pub auto_range_decay: i32,
+    pub auto_range_percentile: i32,
+    pub plot_enable: bool,
+    pub noise: i32,
+
+    pub source_kind: i32,
+    pub source_path: String,
+    pub websocket_urls: String,
+    pub iq_format: i32,
+    pub source_sample_rate: i32,
+    pub source_loop: bool,
+    pub wav_stereo_iq: bool,
+    pub source_address: String,
+    pub rtl_frequency: i32,
+    pub rtl_auto_gain: bool,
+    pub rtl_gain: i32,
+    pub network_mode: i32,
+    pub network_format: i32,
+    pub output_enable: bool,
+    pub output_mode: i32,
+    pub output_address: String,
+    pub output_format: i32,
+
+    pub constellation_enable: bool,
+    pub constellation_points: i32,
+    pub constellation_persistence: i32,
+    pub constellation_decimation: i32,
+
+    pub show_reference: bool,
+    pub reference_difference: bool,
+    pub reference_path: String,
+
+    /// Visibility and y-offset of the channels by index
+    pub channel_visible: Vec<bool>,
+    pub channel_offsets: Vec<i32>,
+
+    pub snapshot_path: String,
+    pub snapshot_width: i32,
+    pub snapshot_height: i32,
+    pub data_path: String,
+    pub data_selection: i32,
+    pub data_frames: i32,
+
+    pub native_plot: bool,
+    pub theme_preset: i32,
+    pub custom_background: String,
+    pub custom_foreground: String,
+    pub custom_trace: String,
+
+    /// Logical window size, not applied on WASM where the page determines the size
+    pub window_width: f32,
+    pub window_height: f32,
+}
+
+impl Settings {
+    /// Read the current settings from the UI
+    pub fn from_app(app: &MainWindow) -> Self {
+        let meta = app.global::<PlotMeta>();
+        let theme = app.global::<Theme>();
+        let window_size = app.window().size().to_logical(app.window().scale_factor());
+        let (channel_visible, channel_offsets) = channels::settings(app);
+        Self {
+            y_axis_min: app.get_y_axis_min(),
+            y_axis_max: app.get_y_axis_max(),
+            y_auto_update: app.get_y_auto_update(),
+            auto_range_headroom: app.get_auto_range_headroom(),
+            auto_range_decay: app.get_auto_range_decay(),
+            auto_range_percentile: app.get_auto_range_percentile(),
+            plot_enable: app.get_plot_enable(),
+            noise: app.get_noise(),
+
+            source_kind: app.get_source_kind(),
+            source_path: app.get_source_path().into(),
+            websocket_urls: app.get_websocket_urls().into(),
+            iq_format: app.get_iq_format(),
+            source_sample_rate: app.get_source_sample_rate(),
+            source_loop: app.get_source_loop(),
+            wav_stereo_iq: app.get_wav_stereo_iq(),
+            source_address: app.get_source_address().into(),
+            rtl_frequency: app.get_rtl_frequency(),
+            rtl_auto_gain: app.get_rtl_auto_gain(),
+            rtl_gain: app.get_rtl_gain(),
+            network_mode: app.get_network_mode(),
+            network_format: app.get_network_format(),
+            output_enable: app.get_output_enable(),
+            output_mode: app.get_output_mode(),
+            output_address: app.get_output_address().into(),
+            output_format: app.get_output_format(),
+
+            constellation_enable: app.get_constellation_enable(),
+            constellation_points: app.get_constellation_points(),
+            constellation_persistence: app.get_constellation_persistence(),
+            constellation_decimation: app.get_constellation_decimation(),
+
+            show_reference: meta.get_show_reference(),
+            reference_difference: meta.get_reference_difference(),
+            reference_path: app.get_reference_path().into(),
+
+            channel_visible,
+            channel_offsets,
+
+            snapshot_path: app.get_snapshot_path().into(),
+            snapshot_width: app.get_snapshot_width(),
+            snapshot_height: app.get_snapshot_height(),
+            data_path: app.get_data_path().into(),
+            data_selection: app.get_data_selection(),
+            data_frames: app.get_data_frames(),
+
+            native_plot: app.get_native_plot(),
+            theme_preset: theme.get_preset(),
+            custom_background: theme.get_custom_background().into(),
+            custom_foreground: theme.get_custom_foreground().into(),
+            custom_trace: theme.get_custom_trace().into(),
+
+            window_width: window_size.width,
+            window_height: window_size.height,
+        }
+    }
+
+    /// Settings of the freshly created UI. The window has no size before it is shown, so the
+    /// size it opens with is used instead.
+    pub fn defaults(app: &MainWindow) -> Self {
+        Self {
+            window_width: app.get_default_width(),
+            window_height: app.get_default_height(),
+            ..Self::from_app(app)
+        }
+    }
+
+    /// Set all controls of the UI to these settings.
+    ///
+    /// Callbacks are not invoked, so the caller must react to changes, e.g., of the theme or
+    /// whether the plot is enabled.
+    pub fn apply(&self, app: &MainWindow) {
+        app.set_y_axis_min(self.y_axis_min);
+        app.set_y_axis_max(self.y_axis_max);
+        app.set_y_auto_update(self.y_auto_update);
+        app.set_auto_range_headroom(self.auto_range_headroom);
+        app.set_auto_range_decay(self.auto_range_decay);
+        app.set_auto_range_percentile(self.auto_range_percentile);
+        app.set_plot_enable(self.plot_enable);
+        app.set_noise(self.noise);
+
+        app.set_source_kind(self.source_kind);
+        app.set_source_path(self.source_path.as_str().into());
+        app.set_websocket_urls(self.websocket_urls.as_str().into());
+        app.set_iq_format(self.iq_format);
+        app.set_source_sample_rate(self.source_sample_rate);
+        app.set_source_loop(self.source_loop);
+        app.set_wav_stereo_iq(self.wav_stereo_iq);
+        app.set_source_address(self.source_address.as_str().into());
+        app.set_rtl_frequency(self.rtl_frequency);
+        app.set_rtl_auto_gain(self.rtl_auto_gain);
+        app.set_rtl_gain(self.rtl_gain);
+        app.set_network_mode(self.network_mode);
+        app.set_network_format(self.network_format);
+        app.set_output_enable(self.output_enable);
+        app.set_output_mode(self.output_mode);
+        app.set_output_address(self.output_address.as_str().into());
+        app.set_output_format(self.output_format);
+
+        app.set_constellation_enable(self.constellation_enable);
+        app.set_constellation_points(self.constellation_points);
+        app.set_constellation_persistence(self.constellation_persistence);
+        app.set_constellation_decimation(self.constellation_decimation);
+
+        let meta = app.global::<PlotMeta>();
+        meta.set_show_reference(self.show_reference);
+        meta.set_reference_difference(self.reference_difference);
+        app.set_reference_path(self.reference_path.as_str().into());
+
+        channels::restore(app, &self.channel_visible, &self.channel_offsets);
+
+        app.set_snapshot_path(self.snapshot_path.as_str().into());
+        app.set_snapshot_width(self.snapshot_width);
+        app.set_snapshot_height(self.snapshot_height);
+        app.set_data_path(self.data_path.as_str().into());
+        app.set_data_selection(self.data_selection);
+        app.set_data_frames(self.data_frames);
+
+        app.set_native_plot(self.native_plot);
+        let theme = app.global::<Theme>();
+        theme.set_preset(self.theme_preset);
+        theme.set_custom_background(self.custom_background.as_str().into());
+        theme.set_custom_foreground(self.custom_foreground.as_str().into());
+        theme.set_custom_trace(self.custom_trace.as_str().into());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.window_width > 0.0 && self.window_height > 0.0 {
+            app.window().set_size(slint::LogicalSize::new(
+                self.window_width,
+                self.window_height,
+            ));
+        }
+    }
+
+    /// Serialize as `key = value` lines, see the [module documentation](self)
+    pub fn to_text(&self) -> String {
+        to_fields(self)
+            .iter()
+            .map(|(key, value)| format!("{key} = {value}\n"))
+            .collect()
+    }
+
+    /// Parse settings written by [`Settings::to_text`], starting from `defaults` for anything
+    /// that is missing or invalid
+    pub fn parse(text: &str, defaults: &Settings) -> Self {
+        let mut fields = to_fields(defaults);
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                warn!("Ignoring invalid settings line: {line}");
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            // Possibly written by a newer or older version
+            let Some(field) = fields.get_mut(key) else {
+                continue;
+            };
+            let Ok(parsed) = serde_json::from_str(value) else {
+                warn!("Ignoring invalid value for setting {key}: {value}");
+                continue;
+            };
+
+            // The value must also fit the type of the field, e.g., an `i32`
+            let previous = std::mem::replace(field, parsed);
+            if Settings::deserialize(&Value::Object(fields.clone())).is_err() {
+                warn!("Ignoring invalid value for setting {key}: {value}");
+                fields[key] = previous;
+            }
+        }
+        Settings::deserialize(&Value::Object(fields)).unwrap_or_else(|_| defaults.clone())
+    }
+
+    /// Load the stored settings, falling back to `defaults` if there are none
+    pub fn load(defaults: &Settings) -> Self {
+        match storage::read() {
+            Some(text) => Self::parse(&text, defaults),
+            None => defaults.clone(),
+        }
+    }
+
+    /// Store the settings for the next session
+    pub fn save(&self) -> Result<()> {
+        storage::write(&self.to_text()).map_err(Error::Settings)
+    }
+}
+
+/// The fields of `settings` by name
+fn to_fields(settings: &Settings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(fields)) => fields,
+        // Serializing only fails for maps with keys that are not strings
+        _ => unreachable!("settings are a struct of plain values"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod storage {
+    use std::{fs, io, path::PathBuf};
+
+    fn path() -> io::Result<PathBuf> {
+        let dir = dirs::config_dir().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "no config directory on this platform",
+            )
+        })?;
+        Ok(dir.join(super::STORAGE_NAME).join("settings.conf"))
+    }
+
+    pub fn read() -> Option<String> {
+        fs::read_to_string(path().ok()?).ok()
+    }
+
+    pub fn write(text: &str) -> io::Result<()> {
+        let path = path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod storage {
+    use std::io;
+
+    fn local_storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "localStorage unavailable"))
+    }
+
+    pub fn read() -> Option<String> {
+        local_storage()
+            .ok()?
+            .get_item(super::STORAGE_NAME)
+            .ok()
+            .flatten()
+    }
+
+    pub fn write(text: &str) -> io::Result<()> {
+        local_storage()?
+            .set_item(super::STORAGE_NAME, text)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("{e:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Settings {
+        Settings {
+            y_axis_min: -100,
+            y_axis_max: 20,
+            y_auto_update: true,
+            auto_range_headroom: 10,
+            auto_range_decay: 5000,
+            auto_range_percentile: 95,
+            plot_enable: false,
+            noise: 10,
+
+            source_kind: 1,
+            source_path: "capture.cf32".into(),
+            websocket_urls: "ws://localhost:9001/, ws://localhost:9002/".into(),
+            iq_format: 0,
+            source_sample_rate: 1000,
+            source_loop: true,
+            wav_stereo_iq: false,
+            source_address: "localhost:1234".into(),
+            rtl_frequency: 100_000,
+            rtl_auto_gain: true,
+            rtl_gain: 0,
+            network_mode: 0,
+            network_format: 0,
+            output_enable: false,
+            output_mode: 0,
+            output_address: "localhost:1235".into(),
+            output_format: 0,
+
+            constellation_enable: false,
+            constellation_points: 1000,
+            constellation_persistence: 1,
+            constellation_decimation: 1,
+
+            show_reference: false,
+            reference_difference: false,
+            reference_path: "reference.csv".into(),
+
+            channel_visible: vec![true, false],
+            channel_offsets: vec![0, -20],
+
+            snapshot_path: "snapshot.png".into(),
+            snapshot_width: 1920,
+            snapshot_height: 1080,
+            data_path: "spectrum.csv".into(),
+            data_selection: 0,
+            data_frames: 10,
+
+            native_plot: false,
+            theme_preset: 0,
+            custom_background: "#ffffff".into(),
+            custom_foreground: "#000000".into(),
+            custom_trace: "#ff0000".into(),
+
+            window_width: 1000.0,
+            window_height: 750.5,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let settings = Settings {
+            source_path: " leading and trailing spaces ".into(),
+            reference_path: "new\nline, \"quotes\" and \\".into(),
+            snapshot_path: "key = value # not a comment".into(),
+            data_path: String::new(),
+            channel_visible: vec![false, true, true],
+            channel_offsets: vec![],
+            ..example()
+        };
+        let text = settings.to_text();
+        assert_eq!(text.lines().count(), to_fields(&settings).len());
+        assert_eq!(Settings::parse(&text, &example()), settings);
+        assert_eq!(Settings::parse("", &settings), settings);
+    }
+
+    #[test]
+    fn skip_invalid_lines() {
+        let text = "\n\
+            # y_axis_min = 0\n\
+            y_axis_max\n\
+            = 5\n\
+            unknown = 1\n\
+            noise = 42\n";
+        let settings = Settings::parse(text, &example());
+        assert_eq!(
+            settings,
+            Settings {
+                noise: 42,
+                ..example()
+            }
+        );
+    }
+
+    #[test]
+    fn keep_defaults_for_invalid_values() {
+        let text = "y_axis_min = ten\n\
+            y_axis_max = 1e12\n\
+            y_auto_update = 1\n\
+            source_path = unquoted\n\
+            channel_visible = [true, 1]\n\
+            channel_offsets = 5\n\
+            window_width = null\n\
+            window_height = 600\n";
+        let settings = Settings::parse(text, &example());
+        assert_eq!(
+            settings,
+            Settings {
+                window_height: 600.0,
+                ..example()
+            }
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Run the sample source while the plot is enabled.
+//!
+//! The [`Supervisor`] makes sure that at most one source (flowgraph, replay or websocket) runs at a
+//! time: enabling the plot while the previous source is still shutting down starts the new one
+//! only after the old one finished. Sources are cancelled cooperatively through their
+//! [`SourceHandle`] and the current state is shown in the GUI.
+
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    future::Future,
+    pin::pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Poll, Waker},
+};
+
+use log::{info, warn};
+use slint::Weak;
+
+use crate::{MainWindow, SourceState};
+
+/// Starts and stops the source `S`, which is called with the window and a handle to run one
+/// source until it is cancelled or fails
+pub struct Supervisor<S> {
+    window: Weak<MainWindow>,
+    source: S,
+    /// Generation of the most recently started source, so outdated ones cannot change the state
+    generation: Arc<AtomicU64>,
+    inner: RefCell<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    /// Cancellation of the source that is still running, if any
+    running: Option<CancelToken>,
+    /// Start again once the running source finished
+    restart: bool,
+}
+
+impl<S, F, E> Supervisor<S>
+where
+    S: Fn(Weak<MainWindow>, SourceHandle) -> F + 'static,
+    F: Future<Output = Result<(), E>> + 'static,
+    E: Display,
+{
+    pub fn new(window: Weak<MainWindow>, source: S) -> Rc<Self> {
+        Rc::new(Self {
+            window,
+            source,
+            generation: Arc::default(),
+            inner: RefCell::default(),
+        })
+    }
+
+    /// Start or stop the source. Must be called on the GUI thread.
+    pub fn set_enabled(self: &Rc<Self>, enabled: bool) {
+        let start = {
+            let inner = &mut *self.inner.borrow_mut();
+            match (&inner.running, enabled) {
+                (None, true) => true,
+                // Already running
+                (Some(token), true) if !token.is_cancelled() => false,
+                // Still stopping, start again once that is done
+                (Some(_), true) => {
+                    inner.restart = true;
+                    false
+                }
+                (Some(token), false) => {
+                    inner.restart = false;
+                    if !token.is_cancelled() {
+                        info!("Stopping source");
+                        token.cancel();
+                        self.set_state(SourceState::Stopping, "");
+                    }
+                    false
+                }
+                (None, false) => false,
+            }
+        };
+        if start {
+            self.start();
+        }
+    }
+
+    /// Stop the source if it is running and start it again once it stopped, e.g., to apply
+    /// changed settings. Must be called on the GUI thread.
+    pub fn restart(self: &Rc<Self>) {
+        if self.inner.borrow().running.is_some() {
+            self.set_enabled(false);
+            self.set_enabled(true);
+        }
+    }
+
+    fn start(self: &Rc<Self>) {
+        info!("Starting source");
+        let token = CancelToken::default();
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.inner.borrow_mut().running = Some(token.clone());
+        self.set_state(SourceState::Starting, "");
+
+        let handle = SourceHandle {
+            window: self.window.clone(),
+            token,
+            generation,
+            current_generation: self.generation.clone(),
+        };
+        let source = (self.source)(self.window.clone(), handle);
+        let supervisor = self.clone();
+        let spawned = slint::spawn_local(async move {
+            let result = source.await;
+            supervisor.finished(result);
+        });
+        if let Err(e) = spawned {
+            self.inner.borrow_mut().running = None;
+            self.set_state(SourceState::Error, &format!("Cannot start source: {e}"));
+        }
+    }
+
+    /// Called when the source returned, either because it was cancelled or because it failed
+    fn finished(self: &Rc<Self>, result: Result<(), E>) {
+        let restart = {
+            let mut inner = self.inner.borrow_mut();
+            inner.running = None;
+            std::mem::take(&mut inner.restart)
+        };
+        match result {
+            Ok(()) => {
+                info!("Source stopped");
+                self.set_state(SourceState::Stopped, "");
+            }
+            Err(e) => {
+                warn!("Source failed: {e}");
+                self.set_state(SourceState::Error, &e.to_string());
+                // Also show it in the status banner, which offers to retry
+                if let Some(app) = self.window.upgrade() {
+                    app.set_error_message(format!("Source failed: {e}").into());
+                }
+            }
+        }
+        if restart {
+            self.start();
+        }
+    }
+
+    fn set_state(&self, state: SourceState, error: &str) {
+        if let Some(app) = self.window.upgrade() {
+            app.set_source_state(state);
+            app.set_source_error(error.into());
+        }
+    }
+}
+
+/// Handed to a running source to find out whether it should stop and to report its state
+#[derive(Clone)]
+pub struct SourceHandle {
+    window: Weak<MainWindow>,
+    token: CancelToken,
+    generation: u64,
+    current_generation: Arc<AtomicU64>,
+}
+
+impl SourceHandle {
+    /// Whether the source should stop as soon as possible
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Wait for `future`, or return `None` as soon as the source is cancelled. The returned future
+    /// does not borrow the handle, so it can be awaited on another thread.
+    pub fn until_cancelled<T>(
+        &self,
+        future: impl Future<Output = T>,
+    ) -> impl Future<Output = Option<T>> {
+        let token = self.token.clone();
+        async move {
+            let mut future = pin!(future);
+            std::future::poll_fn(|cx| {
+                if token.is_cancelled() {
+                    return Poll::Ready(None);
+                }
+                if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                    return Poll::Ready(Some(output));
+                }
+                token.register(cx.waker());
+                // Cancelled while registering
+                if token.is_cancelled() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await
+        }
+    }
+
+    /// Report that the source is up and delivering samples. Can be called from any thread.
+    pub fn set_running(&self) {
+        let token = self.token.clone();
+        let generation = self.generation;
+        let current_generation = self.current_generation.clone();
+        let _ = self.window.upgrade_in_event_loop(move |app| {
+            // Ignore outdated sources and those being stopped
+            if current_generation.load(Ordering::SeqCst) == generation && !token.is_cancelled() {
+                app.set_source_state(SourceState::Running);
+            }
+        });
+    }
+}
+
+/// Cancellation flag that wakes up the tasks waiting in [`SourceHandle::until_cancelled`]
+#[derive(Clone, Default)]
+struct CancelToken(Arc<CancelInner>);
+
+#[derive(Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancelToken {
+    fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        for waker in self.0.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.wakers.lock().unwrap();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
@@ -1,53 +1,332 @@
-use crate::{rendering::render_plot, MainWindow};
-use futures_util::StreamExt;
-use log::info;
+use crate::{
+    error::{self, Error, Result},
+    rendering::{channels, render_plot_channels},
+    supervisor::SourceHandle,
+    ConnectionState, MainWindow,
+};
+use futures_util::{future, StreamExt};
+use log::{debug, info, warn};
 use slint::Weak;
-use std::time::Duration;
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
 use tokio_tungstenite_wasm::{connect, Message};
 
-pub async fn wait_for_samples(window_weak: Weak<MainWindow>) {
+/// Receive and plot spectra from the websockets set up in the GUI, reconnecting until `source` is
+/// cancelled. Each websocket is plotted as a channel of its own.
+pub async fn wait_for_samples(window_weak: Weak<MainWindow>, source: SourceHandle) -> Result<()> {
+    let Some(app) = window_weak.upgrade() else {
+        return Ok(());
+    };
+    let urls = parse_urls(&app.get_websocket_urls());
+    if urls.is_empty() {
+        return Err(Error::Source("no websocket URL given".to_string()));
+    }
+    channels::define(&app, &urls);
+    drop(app);
+
     #[cfg(not(target_arch = "wasm32"))]
-    tokio::runtime::Runtime::new()
-        .unwrap()
-        .spawn(async { websocket_rx(window_weak).await })
-        .await
-        .unwrap();
+    {
+        tokio::runtime::Runtime::new()?
+            .spawn(async { receive_all(window_weak, source, urls).await })
+            .await
+            .map_err(|e| Error::Source(format!("websocket receiver stopped: {e}")))?
+    }
 
     #[cfg(target_arch = "wasm32")]
-    websocket_rx(window_weak).await;
-}
-
-async fn websocket_rx(window_weak: Weak<MainWindow>) -> ! {
-    loop {
-        info!("Waiting for samples...");
-
-        // This "connect" causes an "Uncaught Error: closure invoked recursively or after being dropped"
-        // when compiled to WASM. However, that does not seem to be a problem.
-        if let Ok(socket) = connect("ws://localhost:9001/").await {
-            let (_, mut read) = socket.split();
-
-            while let Some(message) = read.next().await {
-                let values: Vec<f32> = match message {
-                    Ok(Message::Binary(data)) => data
-                        .chunks_exact(4)
-                        .map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]]))
-                        .collect(),
-                    _ => vec![0.0, 0.0, 0.0],
-                };
-
-                window_weak
-                    .upgrade_in_event_loop(move |app| {
-                        if app.get_plot_enable() {
-                            app.set_plot_frame(render_plot(&values, &app))
+    {
+        receive_all(window_weak, source, urls).await
+    }
+}
+
+/// URLs separated by spaces or commas
+fn parse_urls(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Receive from all `urls` at once until `source` is cancelled or the GUI is closed
+async fn receive_all(
+    window_weak: Weak<MainWindow>,
+    source: SourceHandle,
+    urls: Vec<String>,
+) -> Result<()> {
+    let status = Status {
+        connections: Mutex::new(
+            urls.iter()
+                .map(|_| Connection {
+                    state: ConnectionState::None,
+                    details: String::new(),
+                })
+                .collect(),
+        ),
+        malformed_frames: AtomicU32::new(0),
+    };
+    let latest = Mutex::new(vec![None; urls.len()]);
+    let receivers = (0..urls.len()).map(|channel| {
+        let receiver = Receiver {
+            window: window_weak.clone(),
+            source: source.clone(),
+            status: &status,
+            latest: &latest,
+            channel,
+        };
+        receiver.run(&urls[channel])
+    });
+    future::try_join_all(receivers).await?;
+    Ok(())
+}
+
+/// Connection to the websocket of one channel, the first one is the live channel
+struct Receiver<'a> {
+    window: Weak<MainWindow>,
+    source: SourceHandle,
+    status: &'a Status,
+    /// Most recent frame of every channel, the others are plotted along with each frame of the
+    /// first channel
+    latest: &'a Mutex<Vec<Option<Vec<f32>>>>,
+    channel: usize,
+}
+
+impl Receiver<'_> {
+    /// Receive from `url` until the source is cancelled, reconnecting whenever it fails
+    async fn run(self, url: &str) -> Result<()> {
+        let mut backoff = Backoff::default();
+
+        while !self.source.is_cancelled() {
+            info!("Connecting to {url}");
+            self.set_state(ConnectionState::Connecting, format!("connecting to {url}"))?;
+
+            // This "connect" causes an "Uncaught Error: closure invoked recursively or after being dropped"
+            // when compiled to WASM. However, that does not seem to be a problem.
+            let reason = match self.source.until_cancelled(connect(url)).await {
+                None => break,
+                Some(Err(e)) => format!("cannot connect: {e}"),
+                Some(Ok(socket)) => {
+                    info!("Connected to {url}");
+                    self.source.set_running();
+                    self.set_state(ConnectionState::Connected, String::new())?;
+                    backoff.reset();
+                    let (_, mut read) = socket.split();
+                    loop {
+                        let Some(message) = self.source.until_cancelled(read.next()).await else {
+                            break "stopped".to_string();
+                        };
+                        match message {
+                            Some(Ok(Message::Binary(data))) => match parse_frame(&data) {
+                                Some(values) => self.plot(values)?,
+                                None => {
+                                    warn!("Ignoring malformed frame of {} bytes", data.len());
+                                    self.status.malformed_frames.fetch_add(1, Ordering::Relaxed);
+                                    self.set_state(ConnectionState::Connected, String::new())?;
+                                }
+                            },
+                            // Pings are answered by the websocket implementation itself
+                            Some(Ok(Message::Text(text))) => {
+                                debug!("Ignoring text message: {text}")
+                            }
+                            Some(Ok(Message::Close(frame))) => {
+                                break match frame {
+                                    Some(frame) => {
+                                        format!(
+                                            "closed by server ({:?}): {}",
+                                            frame.code, frame.reason
+                                        )
+                                    }
+                                    None => "closed by server".to_string(),
+                                }
+                            }
+                            Some(Err(e)) => break format!("connection lost: {e}"),
+                            None => break "connection closed".to_string(),
                         }
-                    })
-                    .unwrap();
+                    }
+                }
+            };
+            if self.source.is_cancelled() {
+                break;
             }
+
+            let delay = backoff.next_delay();
+            warn!("Websocket {url} {reason}, retrying in {delay:?}");
+            self.set_state(
+                ConnectionState::Reconnecting,
+                format!("{url} {reason}, retrying in {:.1} s", delay.as_secs_f32()),
+            )?;
+
+            #[cfg(not(target_arch = "wasm32"))]
+            let sleep = tokio::time::sleep(delay);
+            #[cfg(target_arch = "wasm32")]
+            let sleep = gloo_timers::future::sleep(delay);
+            self.source.until_cancelled(sleep).await;
+        }
+
+        self.set_state(ConnectionState::Disconnected, String::new())
+    }
+
+    /// Plot the frames of all channels whenever the first one has a new frame, the others are
+    /// only stored until then
+    fn plot(&self, values: Vec<f32>) -> Result<()> {
+        let mut latest = self.latest.lock().unwrap();
+        if self.channel > 0 {
+            latest[self.channel] = Some(values);
+            return Ok(());
         }
+        let others: Vec<Option<Vec<f32>>> = latest[1..].to_vec();
+        drop(latest);
+        self.window.upgrade_in_event_loop(move |app| {
+            if app.get_plot_enable() {
+                // Channels without a frame yet stay empty, so that the others keep their index
+                let mut frames = vec![values.as_slice()];
+                frames.extend(
+                    others
+                        .iter()
+                        .map(|frame| frame.as_deref().unwrap_or_default()),
+                );
+                if let Err(e) = render_plot_channels(&frames, &app) {
+                    error::report(&app, &e);
+                }
+            }
+        })?;
+        Ok(())
+    }
+
+    fn set_state(&self, state: ConnectionState, details: String) -> Result<()> {
+        self.status.set(&self.window, self.channel, state, details)
+    }
+}
+
+/// Decode a frame of little-endian `f32` levels. `None` if it is empty or not a whole number of
+/// values.
+fn parse_frame(data: &[u8]) -> Option<Vec<f32>> {
+    if data.is_empty() || !data.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(
+        data.chunks_exact(4)
+            .map(|f| f32::from_le_bytes([f[0], f[1], f[2], f[3]]))
+            .collect(),
+    )
+}
+
+/// Connection states of all channels and statistics shown in the GUI
+struct Status {
+    connections: Mutex<Vec<Connection>>,
+    /// Frames that could not be decoded since the source was started
+    malformed_frames: AtomicU32,
+}
+
+/// State of the websocket of one channel
+struct Connection {
+    state: ConnectionState,
+    /// Why the connection was lost, empty once it is connected
+    details: String,
+}
+
+impl Status {
+    /// Set the `state` of the connection of `channel` with `details`, e.g., why it was lost. The
+    /// GUI shows the most pressing state of all connections: reconnecting, connecting, connected
+    /// or disconnected, with the details of the connections in that state. Can be called from any
+    /// thread.
+    fn set(
+        &self,
+        window: &Weak<MainWindow>,
+        channel: usize,
+        state: ConnectionState,
+        details: String,
+    ) -> Result<()> {
+        let (state, details) = {
+            let mut connections = self.connections.lock().unwrap();
+            connections[channel].state = state;
+            connections[channel].details = details;
+
+            let urgency = |state: ConnectionState| match state {
+                ConnectionState::Reconnecting => 3,
+                ConnectionState::Connecting => 2,
+                ConnectionState::Connected => 1,
+                ConnectionState::Disconnected | ConnectionState::None => 0,
+            };
+            let state = connections
+                .iter()
+                .map(|connection| connection.state)
+                .max_by_key(|state| urgency(*state))
+                .unwrap_or(state);
+            let details: Vec<&str> = connections
+                .iter()
+                .filter(|connection| urgency(connection.state) == urgency(state))
+                .map(|connection| connection.details.as_str())
+                .filter(|details| !details.is_empty())
+                .collect();
+            (state, details.join("; "))
+        };
+        let malformed_frames = self.malformed_frames.load(Ordering::Relaxed) as i32;
+        window.upgrade_in_event_loop(move |app| {
+            app.set_connection_state(state);
+            app.set_connection_details(details.into());
+            app.set_malformed_frames(malformed_frames);
+        })?;
+        Ok(())
+    }
+}
+
+/// Exponentially growing delays between reconnection attempts
+#[derive(Default)]
+struct Backoff {
+    attempts: u32,
+}
+
+impl Backoff {
+    const INITIAL: Duration = Duration::from_millis(100);
+    const MAX: Duration = Duration::from_secs(10);
+
+    /// Delay before the next attempt: doubled with every failed attempt up to [`Backoff::MAX`],
+    /// of which a random half is left out, so many clients do not reconnect all at once
+    fn next_delay(&mut self) -> Duration {
+        let delay = Self::INITIAL
+            .saturating_mul(1 << self.attempts.min(16))
+            .min(Self::MAX);
+        self.attempts += 1;
+        delay.mul_f64(0.5 + 0.5 * random())
+    }
+
+    /// Start over with short delays after a successful connection
+    fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+/// Random number in `0.0..1.0`, good enough for jitter
+#[cfg(not(target_arch = "wasm32"))]
+fn random() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    // Every `RandomState` is seeded differently
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(target_arch = "wasm32")]
+fn random() -> f64 {
+    js_sys::Math::random()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        #[cfg(not(target_arch = "wasm32"))]
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        #[cfg(target_arch = "wasm32")]
-        gloo_timers::future::sleep(Duration::from_millis(100)).await;
+    #[test]
+    fn parse_url_lists() {
+        assert_eq!(parse_urls("ws://localhost:9001/"), ["ws://localhost:9001/"]);
+        assert_eq!(
+            parse_urls(" ws://a:1/, ws://b:2/ ws://c:3/,,"),
+            ["ws://a:1/", "ws://b:2/", "ws://c:3/"]
+        );
+        assert!(parse_urls(" , ").is_empty());
     }
 }
@@ -0,0 +1,304 @@
+//! Golden-image tests of the spectrum renderer.
+//!
+//! Fixed synthetic spectra are rendered through the SVG and the bitmap path and compared against
+//! the reference images in `tests/golden` within a tolerance, so small differences, e.g., in font
+//! rendering between machines, do not fail the tests.
+//!
+//! After an intended change of the plots, re-bless the references with
+//! `BLESS=1 cargo test --test golden` and review the new images before committing them.
+//! Images that do not match are written to `target/tmp/golden` for inspection.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use slint_futuresdr_demo::{
+    error::Error,
+    rendering::{
+        config::{ChannelStyle, PlotConfig, YRange},
+        render, render_channels,
+        snapshot::SnapshotFormat,
+        theme::PlotTheme,
+    },
+};
+
+/// Number of bins of the synthetic spectra
+const BINS: usize = 512;
+
+/// Largest allowed difference of a coordinate in an SVG, in pixels
+const SVG_TOLERANCE: f64 = 2.0;
+/// Largest difference of a color channel for two pixels to be considered the same
+const PIXEL_TOLERANCE: u8 = 16;
+/// Largest allowed fraction of pixels that are not the same
+const MAX_DIFFERENT_PIXELS: f64 = 0.02;
+
+fn config() -> PlotConfig {
+    PlotConfig {
+        width: 640,
+        height: 360,
+        y_range: YRange::Fixed(0.0, 100.0),
+        theme: PlotTheme::light(),
+        ..PlotConfig::default()
+    }
+}
+
+/// Deterministic noise in `0.0..1.0`, so the references do not depend on a random generator
+fn noise(bins: usize) -> Vec<f32> {
+    let mut state: u32 = 0x1234_5678;
+    (0..bins)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        })
+        .collect()
+}
+
+#[test]
+fn tone() {
+    let data: Vec<f32> = noise(BINS)
+        .iter()
+        .enumerate()
+        .map(|(bin, n)| {
+            let offset = bin as f32 - 100.0;
+            10.0 + 5.0 * n + 80.0 * (-offset * offset / 8.0).exp()
+        })
+        .collect();
+    check("tone", &data, &config());
+}
+
+#[test]
+fn tone_auto_range() {
+    let data: Vec<f32> = (0..BINS)
+        .map(|bin| 20.0 + 30.0 * (-((bin as f32 - 300.0) / 3.0).powi(2)).exp())
+        .collect();
+    let config = PlotConfig {
+        y_range: YRange::Fit,
+        ..config()
+    };
+    check("tone_auto_range", &data, &config);
+}
+
+#[test]
+fn flat_noise() {
+    let data: Vec<f32> = noise(BINS).iter().map(|n| 30.0 + 20.0 * n).collect();
+    check("flat_noise", &data, &config());
+}
+
+#[test]
+fn nan_bins() {
+    let mut data: Vec<f32> = noise(BINS).iter().map(|n| 40.0 + 10.0 * n).collect();
+    for bin in [0, 1, 200, 201, 202, BINS - 1] {
+        data[bin] = f32::NAN;
+    }
+    data[300] = f32::INFINITY;
+    check("nan_bins", &data, &config());
+}
+
+#[test]
+fn empty() {
+    check("empty", &[], &config());
+}
+
+#[test]
+fn two_channels() {
+    let tone = |center: f32| -> Vec<f32> {
+        noise(BINS)
+            .iter()
+            .enumerate()
+            .map(|(bin, n)| 10.0 + 5.0 * n + 60.0 * (-(bin as f32 - center).powi(2) / 8.0).exp())
+            .collect()
+    };
+    let config = PlotConfig {
+        channels: vec![
+            ChannelStyle {
+                name: "Live".to_string(),
+                ..ChannelStyle::new(0)
+            },
+            ChannelStyle {
+                name: "Shifted".to_string(),
+                offset: 20.0,
+                ..ChannelStyle::new(1)
+            },
+        ],
+        ..config()
+    };
+    check_channels("two_channels", &[&tone(100.0), &tone(350.0)], &config);
+}
+
+#[test]
+fn zero_size() {
+    let config = PlotConfig {
+        height: 0,
+        ..config()
+    };
+    for format in [SnapshotFormat::Svg, SnapshotFormat::Png] {
+        let result = render(&noise(BINS), &config, format);
+        assert!(matches!(result, Err(Error::InvalidSize { .. })));
+    }
+}
+
+/// Render `data` in both formats and compare against the references named `name`
+fn check(name: &str, data: &[f32], config: &PlotConfig) {
+    check_channels(name, &[data], config);
+}
+
+/// Render the `frames` of several channels like [`check`]
+fn check_channels(name: &str, frames: &[&[f32]], config: &PlotConfig) {
+    let mut failures = Vec::new();
+    for format in [SnapshotFormat::Svg, SnapshotFormat::Png] {
+        let image = render_channels(frames, config, format)
+            .unwrap_or_else(|e| panic!("cannot render {name}: {e}"))
+            .image;
+        let file_name = format!("{name}.{}", extension(format));
+        if let Err(e) = compare_with_reference(&file_name, &image, format) {
+            failures.push(format!("{file_name}: {e}"));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+fn extension(format: SnapshotFormat) -> &'static str {
+    match format {
+        SnapshotFormat::Svg => "svg",
+        SnapshotFormat::Png => "png",
+    }
+}
+
+fn compare_with_reference(
+    file_name: &str,
+    actual: &[u8],
+    format: SnapshotFormat,
+) -> Result<(), String> {
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(file_name);
+
+    if env::var_os("BLESS").is_some() {
+        fs::create_dir_all(reference_path.parent().unwrap()).unwrap();
+        fs::write(&reference_path, actual).unwrap();
+        return Ok(());
+    }
+
+    let reference = fs::read(&reference_path).map_err(|e| {
+        format!(
+            "cannot read reference {} ({e}), create it with `BLESS=1 cargo test --test golden`",
+            reference_path.display()
+        )
+    })?;
+    let result = match format {
+        SnapshotFormat::Svg => compare_svg(
+            &String::from_utf8_lossy(&reference),
+            &String::from_utf8_lossy(actual),
+        ),
+        SnapshotFormat::Png => compare_png(&reference, actual),
+    };
+    result.map_err(|e| {
+        format!(
+            "{e}, actual image written to {}",
+            save_actual(file_name, actual)
+        )
+    })
+}
+
+/// Keep a mismatching image around for inspection
+fn save_actual(file_name: &str, actual: &[u8]) -> String {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    let path = dir.join(file_name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, actual).unwrap();
+    path.display().to_string()
+}
+
+/// The SVGs must be identical except for numbers, which may differ by [`SVG_TOLERANCE`]
+fn compare_svg(reference: &str, actual: &str) -> Result<(), String> {
+    let reference = svg_tokens(reference);
+    let actual = svg_tokens(actual);
+    if reference.len() != actual.len() {
+        return Err(format!(
+            "different structure: {} tokens instead of {}",
+            actual.len(),
+            reference.len()
+        ));
+    }
+    for (reference, actual) in reference.iter().zip(&actual) {
+        let matches = match (reference, actual) {
+            (SvgToken::Number(r), SvgToken::Number(a)) => (r - a).abs() <= SVG_TOLERANCE,
+            (r, a) => r == a,
+        };
+        if !matches {
+            return Err(format!("found {actual:?} instead of {reference:?}"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum SvgToken<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+/// Split an SVG into numbers and the text between them
+fn svg_tokens(svg: &str) -> Vec<SvgToken<'_>> {
+    let is_number_char = |c: char| c.is_ascii_digit() || c == '.' || c == '-';
+    let mut tokens = Vec::new();
+    let mut rest = svg;
+    while !rest.is_empty() {
+        let split = if rest.starts_with(is_number_char) {
+            rest.find(|c| !is_number_char(c)).unwrap_or(rest.len())
+        } else {
+            rest.find(is_number_char).unwrap_or(rest.len())
+        };
+        let (token, tail) = rest.split_at(split.max(1));
+        tokens.push(match token.parse() {
+            Ok(number) => SvgToken::Number(number),
+            Err(_) => SvgToken::Text(token),
+        });
+        rest = tail;
+    }
+    tokens
+}
+
+/// The images must have the same size and at most [`MAX_DIFFERENT_PIXELS`] may differ by more
+/// than [`PIXEL_TOLERANCE`] in any channel
+fn compare_png(reference: &[u8], actual: &[u8]) -> Result<(), String> {
+    let (reference_size, reference) = decode_png(reference)?;
+    let (actual_size, actual) = decode_png(actual)?;
+    if reference_size != actual_size {
+        return Err(format!(
+            "size {actual_size:?} instead of {reference_size:?}"
+        ));
+    }
+
+    let different = reference
+        .chunks_exact(3)
+        .zip(actual.chunks_exact(3))
+        .filter(|(r, a)| {
+            r.iter()
+                .zip(*a)
+                .any(|(r, a)| r.abs_diff(*a) > PIXEL_TOLERANCE)
+        })
+        .count();
+    let fraction = different as f64 / (reference.len() / 3).max(1) as f64;
+    if fraction > MAX_DIFFERENT_PIXELS {
+        return Err(format!("{:.2} % of the pixels differ", fraction * 100.0));
+    }
+    Ok(())
+}
+
+/// Size and RGB8 pixels of a PNG written by the renderer
+fn decode_png(bytes: &[u8]) -> Result<((u32, u32), Vec<u8>), String> {
+    let mut reader = png::Decoder::new(bytes)
+        .read_info()
+        .map_err(|e| format!("invalid PNG: {e}"))?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut pixels)
+        .map_err(|e| format!("invalid PNG: {e}"))?;
+    if info.color_type != png::ColorType::Rgb || info.bit_depth != png::BitDepth::Eight {
+        return Err(format!("unexpected PNG format {:?}", info.color_type));
+    }
+    pixels.truncate(info.buffer_size());
+    Ok(((info.width, info.height), pixels))
+}